@@ -0,0 +1,124 @@
+use crate::structures::{
+    clause::{clause_vec::ClauseVec, Clause},
+    level::LevelIndex,
+    literal::{Literal, LiteralSource},
+    solve::ClauseKey,
+};
+
+/// Ways in which a formula may fail to be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// A literal without a variable, e.g. `-`
+    NoVariable,
+    /// A literal which is not a non-zero integer
+    BadLiteral(String),
+    /// A preface which is not of the form `p cnf <variables> <clauses>`
+    BadPreface(String),
+    /// A weight which is not a non-negative integer
+    BadWeight(String),
+    /// The degree of a cardinality constraint which is not a non-negative integer
+    BadDegree(String),
+    /// A pseudo-Boolean term which is not a coefficient followed by a single literal
+    BadTerm(String),
+    /// A pseudo-Boolean constraint without a relation or a degree
+    BadConstraint(String),
+    /// The input ended before the final clause was closed with `0`
+    UnterminatedClause,
+}
+
+/// Ways in which a solve may fail.
+/// Aside from parse errors, each of these is the violation of some invariant of the solver, and details of the violation are kept for diagnosis.
+#[derive(Debug)]
+pub enum SolverError {
+    /// The formula could not be parsed, with the line of the failure
+    Parse { line: usize, error: ParseError },
+    /// A variable was named which does not occur in the formula
+    UnknownVariable(String),
+    /// An attempt to store a clause without any literals
+    EmptyClause,
+    /// A literal was given a value when the literal already had that value
+    Restatement {
+        literal: Literal,
+        source: LiteralSource,
+    },
+    /// A literal was given a value when the negation of the literal already had that value
+    ConflictingLiteral {
+        literal: Literal,
+        source: LiteralSource,
+    },
+    /// A second choice was made on a single level
+    MultipleChoices { level: LevelIndex },
+    /// Analysis of a conflict did not lead to a clause which asserts some literal
+    NoAssertion {
+        level: LevelIndex,
+        conflict: ClauseKey,
+        resolution_trail: Vec<ClauseKey>,
+        resolved_clause: ClauseVec,
+    },
+    /// No level to backjump to could be determined from the asserting clause
+    DecisionLevel { clause: ClauseVec },
+    /// An attempt to forget a clause of the formula
+    ForgetFormulaClause(ClauseKey),
+    /// A clause was deleted while the clause remained the reason for some literal
+    ReasonDeleted(ClauseKey),
+    /// A solve of a portfolio panicked
+    PortfolioPanic,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::NoVariable => write!(f, "no variable when reading literal"),
+            ParseError::BadLiteral(string) => write!(f, "unable to read literal '{string}'"),
+            ParseError::BadPreface(string) => write!(f, "puzzled by preface '{string}'"),
+            ParseError::BadWeight(string) => write!(f, "unable to read weight '{string}'"),
+            ParseError::BadDegree(string) => write!(f, "unable to read degree '{string}'"),
+            ParseError::BadTerm(string) => write!(f, "unable to read term '{string}'"),
+            ParseError::BadConstraint(string) => write!(f, "puzzled by constraint '{string}'"),
+            ParseError::UnterminatedClause => write!(f, "final clause is not closed with 0"),
+        }
+    }
+}
+
+impl std::fmt::Display for SolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SolverError::Parse { line, error } => write!(f, "parse failure on line {line}: {error}"),
+            SolverError::UnknownVariable(name) => write!(f, "unknown variable '{name}'"),
+            SolverError::EmptyClause => write!(f, "attempt to store an empty clause"),
+            SolverError::Restatement { literal, source } => {
+                write!(f, "restatement of {literal} via {source:?}")
+            }
+            SolverError::ConflictingLiteral { literal, source } => {
+                write!(f, "conflict given {literal} via {source:?}")
+            }
+            SolverError::MultipleChoices { level } => {
+                write!(f, "multiple choices made on level {level}")
+            }
+            SolverError::NoAssertion {
+                level,
+                conflict,
+                resolution_trail,
+                resolved_clause,
+            } => write!(
+                f,
+                "no assertion from conflict {conflict:?} at level {level}, resolved to {} via {resolution_trail:?}",
+                resolved_clause.as_string()
+            ),
+            SolverError::DecisionLevel { clause } => {
+                write!(f, "no decision level for {}", clause.as_string())
+            }
+            SolverError::ForgetFormulaClause(key) => {
+                write!(f, "attempt to forget formula clause {key:?}")
+            }
+            SolverError::ReasonDeleted(key) => {
+                write!(f, "the reason {key:?} for a literal was deleted")
+            }
+            SolverError::PortfolioPanic => write!(f, "a solve of the portfolio panicked"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::error::Error for SolverError {}
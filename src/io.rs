@@ -1,75 +1,408 @@
-use std::char;
+use crate::{
+    errors::{ParseError, SolverError},
+    structures::{
+        formula::Formula,
+        literal::Literal,
+        pseudo_boolean::{LinearConstraint, PbEncoding, Relation},
+    },
+};
 
-use crate::structures::formula::Formula;
+impl Formula {
+    /// Reads a formula in DIMACS CNF form.
+    /// A clause may span multiple lines, and is closed by `0`.
+    /// The preface is optional, though if present it must be of the form `p cnf <variables> <clauses>`.
+    /// A comment of the form `c p show <variables> 0` adds the variables to the projection of the formula.
+    ///
+    /// XOR constraints may be given as in CryptoMiniSat, with a line `x <literals> 0` requiring an odd count of the literals to be true.
+    ///
+    /// Cardinality constraints may be given as in KNF, with a line `k <degree> <literals> 0` requiring at least `degree` of the literals to be true, and a preface of the form `p knf <variables> <constraints>`.
+    ///
+    /// Cubes may be given as in iCNF, with a line `a <literals> 0` and a preface of the form `p inccnf`.
+    pub fn from_dimacs(string: &str) -> Result<Formula, SolverError> {
+        let mut the_formula = Formula::new();
+        let mut the_clause = String::new();
+        let mut line_number = 0;
+
+        for (index, line) in string.lines().enumerate() {
+            line_number = index + 1;
+            let parse_error = |error| SolverError::Parse {
+                line: line_number,
+                error,
+            };
+
+            let line = line.trim();
+            match line.chars().next() {
+                None => continue,
+                Some('c') => {
+                    let mut parts = line.split_whitespace().skip(1);
+                    if parts.next() == Some("p") && parts.next() == Some("show") {
+                        let mut shown = String::new();
+                        for item in parts {
+                            match item.parse::<isize>() {
+                                Ok(0) => break,
+                                Ok(v) if v > 0 => {
+                                    shown.push_str(item);
+                                    shown.push(' ');
+                                }
+                                _ => {
+                                    return Err(parse_error(ParseError::BadLiteral(
+                                        item.to_string(),
+                                    )))
+                                }
+                            }
+                        }
+                        the_formula.add_projection(&shown).map_err(parse_error)?;
+                    }
+                }
+                // some benchmarks close with a '%' line, after which nothing is read
+                Some('%') => break,
+                Some('p') => {
+                    let preface_parts = line.split_whitespace().collect::<Vec<_>>();
+                    if preface_parts == ["p", "inccnf"] {
+                        continue;
+                    }
+                    let well_formed = preface_parts.len() == 4
+                        && (preface_parts[1] == "cnf" || preface_parts[1] == "knf")
+                        && preface_parts[2].parse::<usize>().is_ok()
+                        && preface_parts[3].parse::<usize>().is_ok();
+                    if !well_formed {
+                        return Err(parse_error(ParseError::BadPreface(line.to_string())));
+                    }
+                    the_formula.preface_variable_count = preface_parts[2].parse().ok();
+                }
+                Some('x') => {
+                    let literals =
+                        closed_literals(line[1..].split_whitespace()).map_err(parse_error)?;
+                    the_formula.add_xor_clause(&literals).map_err(parse_error)?;
+                }
+                Some('a') => {
+                    let literals =
+                        closed_literals(line[1..].split_whitespace()).map_err(parse_error)?;
+                    the_formula.add_cube(&literals).map_err(parse_error)?;
+                }
+                Some('k') => {
+                    let mut items = line.split_whitespace().skip(1);
+                    let degree = items.next().unwrap_or_default();
+                    let degree = match degree.parse::<usize>() {
+                        Ok(degree) => degree,
+                        Err(_) => {
+                            return Err(parse_error(ParseError::BadDegree(degree.to_string())))
+                        }
+                    };
+                    let literals = closed_literals(items).map_err(parse_error)?;
+                    the_formula
+                        .add_cardinality_constraint(degree, &literals)
+                        .map_err(parse_error)?;
+                }
+                Some(_) => {
+                    for item in line.split_whitespace() {
+                        match item.parse::<isize>() {
+                            Ok(0) => {
+                                the_formula.add_clause(&the_clause).map_err(parse_error)?;
+                                the_clause.clear();
+                            }
+                            Ok(_) => {
+                                the_clause.push_str(item);
+                                the_clause.push(' ');
+                            }
+                            Err(_) => {
+                                return Err(parse_error(ParseError::BadLiteral(item.to_string())))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if !the_clause.is_empty() {
+            return Err(SolverError::Parse {
+                line: line_number,
+                error: ParseError::UnterminatedClause,
+            });
+        }
+
+        Ok(the_formula)
+    }
+}
+
+/// The literals of a line closed by `0`, separated by spaces.
+fn closed_literals<'a>(items: impl Iterator<Item = &'a str>) -> Result<String, ParseError> {
+    let mut literals = String::new();
+    for item in items {
+        match item.parse::<isize>() {
+            Ok(0) => return Ok(literals),
+            Ok(_) => {
+                literals.push_str(item);
+                literals.push(' ');
+            }
+            Err(_) => return Err(ParseError::BadLiteral(item.to_string())),
+        }
+    }
+    Err(ParseError::UnterminatedClause)
+}
 
 impl Formula {
-    pub fn from_dimacs(string: &str) -> Formula {
-        let mut the_solve = Formula::new();
-        let mut from = 0;
-        let mut to = 0;
-        let mut reading_comment = false;
-        let mut reading_literal = false;
-        while let Some(ch) = string.chars().nth(to) {
-            if !reading_literal {
-                if ['-', '1', '2', '3', '4', '5', '6', '7', '8', '9'].contains(&ch) {
-                    reading_literal = true
-                } else if ch == '0' {
-                    if !reading_comment {
-                        the_solve.add_clause(&string[from..to])
+    /// Reads a weighted formula in WCNF form, either with a preface `p wcnf <variables> <clauses> <top>` or in the later form without a preface where hard clauses begin with `h`.
+    /// Each clause is on a line of its own, and is closed by `0`.
+    /// Clauses with a weight of at least the top weight of the preface are hard.
+    pub fn from_wcnf(string: &str) -> Result<Formula, SolverError> {
+        let mut the_formula = Formula::new();
+        let mut top = None;
+
+        for (index, line) in string.lines().enumerate() {
+            let parse_error = |error| SolverError::Parse {
+                line: index + 1,
+                error,
+            };
+
+            let line = line.trim();
+            let mut items = line.split_whitespace();
+            let weight = match items.next() {
+                None => continue,
+                Some(first) if first.starts_with('c') => continue,
+                Some("p") => {
+                    let preface_parts = line.split_whitespace().collect::<Vec<_>>();
+                    let well_formed = (preface_parts.len() == 4 || preface_parts.len() == 5)
+                        && preface_parts[1] == "wcnf"
+                        && preface_parts[2..]
+                            .iter()
+                            .all(|part| part.parse::<u64>().is_ok());
+                    if !well_formed {
+                        return Err(parse_error(ParseError::BadPreface(line.to_string())));
+                    }
+                    the_formula.preface_variable_count = preface_parts[2].parse().ok();
+                    top = preface_parts
+                        .get(4)
+                        .and_then(|part| part.parse::<u64>().ok());
+                    continue;
+                }
+                Some("h") => None,
+                Some(weight) => match weight.parse::<u64>() {
+                    Ok(w) if top.is_some_and(|t| w >= t) => None,
+                    Ok(w) => Some(w),
+                    Err(_) => return Err(parse_error(ParseError::BadWeight(weight.to_string()))),
+                },
+            };
+
+            let mut the_clause = String::new();
+            let mut closed = false;
+            for item in items {
+                match item.parse::<isize>() {
+                    Ok(0) => {
+                        closed = true;
+                        break;
+                    }
+                    Ok(_) => {
+                        the_clause.push_str(item);
+                        the_clause.push(' ');
                     }
-                    from = to + 1;
+                    Err(_) => return Err(parse_error(ParseError::BadLiteral(item.to_string()))),
                 }
             }
-            if reading_literal && ch.is_whitespace() {
-                reading_literal = false
+            if !closed {
+                return Err(parse_error(ParseError::UnterminatedClause));
+            }
+
+            match weight {
+                None => the_formula.add_clause(&the_clause),
+                Some(w) => the_formula.add_soft_clause(w, &the_clause),
             }
+            .map_err(parse_error)?;
+        }
+
+        Ok(the_formula)
+    }
+}
+
+impl Formula {
+    /// Reads linear pseudo-Boolean constraints in OPB form, encoding each constraint to clauses with the given encoding.
+    /// Each constraint is a sequence of terms `<coefficient> <literal>` followed by a relation `>=`, `<=`, or `=` and a degree, and is closed by `;`.
+    /// A literal is the name of a variable, negated by a prefix `~`, and lines beginning with `*` are comments.
+    ///
+    /// An objective `min: <terms> ;` is kept as soft clauses, one for each term, together with a cost offset for negative coefficients.
+    pub fn from_opb(string: &str, encoding: PbEncoding) -> Result<Formula, SolverError> {
+        let mut the_formula = Formula::new();
+        let mut statement = vec![];
+        let mut statement_line = 0;
 
-            if ch == 'c' {
-                reading_comment = true;
-                from += 1
-            } else if ch == 0xA as char {
-                // newline check
-                from = to;
-                reading_comment = false;
-            } else if !reading_comment && ch == 'p' {
-                loop {
-                    to += 1;
-                    if let Some(other_ch) = string.chars().nth(to) {
-                        if other_ch == 0xA as char {
-                            break;
+        for (index, line) in string.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('*') {
+                continue;
+            }
+            for item in line.split_whitespace() {
+                if statement.is_empty() {
+                    statement_line = index + 1;
+                }
+                match item.strip_suffix(';') {
+                    None => statement.push(item),
+                    Some(last) => {
+                        if !last.is_empty() {
+                            statement.push(last);
                         }
-                    } else {
-                        panic!("IO: Parse failure");
+                        if !statement.is_empty() {
+                            the_formula
+                                .read_opb_statement(&statement, encoding)
+                                .map_err(|error| SolverError::Parse {
+                                    line: statement_line,
+                                    error,
+                                })?;
+                        }
+                        statement.clear();
                     }
                 }
-                let the_preface = &string[from..to];
-                let preface_parts = the_preface.split_whitespace().collect::<Vec<_>>();
-                if preface_parts.len() != 4 {
-                    panic!("IO: Puzzled by preface length");
-                } else if Some(&"p") != preface_parts.first()
-                    || Some(&"cnf") != preface_parts.get(1)
-                {
-                    panic!("IO: Puzzled by preface format");
+            }
+        }
+
+        if !statement.is_empty() {
+            return Err(SolverError::Parse {
+                line: statement_line,
+                error: ParseError::BadConstraint(statement.join(" ")),
+            });
+        }
+
+        Ok(the_formula)
+    }
+
+    fn read_opb_statement(
+        &mut self,
+        items: &[&str],
+        encoding: PbEncoding,
+    ) -> Result<(), ParseError> {
+        if items[0] == "min:" {
+            for (coefficient, literal) in self.read_opb_terms(&items[1..])? {
+                // a term is a cost if the literal is true, and a negative term is a cost if the literal is false
+                match coefficient {
+                    c if c >= 0 => self.soft_clauses.push((c as u64, vec![literal.negate()])),
+                    c => {
+                        self.soft_clauses.push((c.unsigned_abs(), vec![literal]));
+                        self.cost_offset += c;
+                    }
                 }
-                let _variables = match preface_parts.get(2) {
-                    Some(count) => match count.parse::<usize>() {
-                        Ok(count_number) => count_number,
-                        Err(_) => panic!("IO: Parse failure"),
-                    },
-                    None => panic!("IO: Parse failure"),
-                };
-                let _clauses = match preface_parts.get(3) {
-                    Some(count) => match count.parse::<usize>() {
-                        Ok(count_number) => count_number,
-                        Err(_) => panic!("IO: Parse failure"),
-                    },
-                    None => panic!("IO: Parse failure"),
-                };
-                from = to
             }
+            return Ok(());
+        }
+
+        let bad_constraint = || ParseError::BadConstraint(items.join(" "));
+        let relation_index = items
+            .iter()
+            .position(|item| matches!(*item, ">=" | "<=" | "="))
+            .ok_or_else(bad_constraint)?;
+        let relation = match items[relation_index] {
+            ">=" => Relation::AtLeast,
+            "<=" => Relation::AtMost,
+            _ => Relation::Equal,
+        };
+        let degree = match &items[relation_index + 1..] {
+            [degree] => degree.parse::<i64>().map_err(|_| bad_constraint())?,
+            _ => return Err(bad_constraint()),
+        };
+
+        let terms = self.read_opb_terms(&items[..relation_index])?;
+        for constraint in LinearConstraint::from_terms(&terms, relation, degree) {
+            self.add_linear_constraint(constraint, encoding);
+        }
+        Ok(())
+    }
+
+    fn read_opb_terms(&mut self, items: &[&str]) -> Result<Vec<(i64, Literal)>, ParseError> {
+        let mut terms = vec![];
+        // terms are read in pairs, so a product of literals leaves some literal in place of a coefficient
+        for term in items.chunks(2) {
+            let bad_term = || ParseError::BadTerm(term.join(" "));
+            let (coefficient, name) = match term {
+                [coefficient, name] => (coefficient.parse::<i64>().map_err(|_| bad_term())?, *name),
+                _ => return Err(bad_term()),
+            };
+            let literal = match name.strip_prefix('~') {
+                Some(negated) => Literal::from_string(negated, &mut self.variables)?.negate(),
+                None => Literal::from_string(name, &mut self.variables)?,
+            };
+            terms.push((coefficient, literal));
+        }
+        Ok(terms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimacs() {
+        let formula = Formula::from_dimacs("c a comment\np cnf 3 2\n1 -2\n3 0\n-1 2 0\n").unwrap();
+        assert_eq!(formula.clause_count(), 2);
+        assert_eq!(formula.variable_count(), 3);
+        assert_eq!(formula.preface_variable_count, Some(3));
+
+        let formula = Formula::from_dimacs("c p show 4 2 0\n1 -2 0\n").unwrap();
+        assert_eq!(formula.variable_count(), 3);
+        assert_eq!(formula.projection, Some(vec![0, 1]));
+
+        match Formula::from_dimacs("p cnf 3 2\n1 -2 0\n1 x 0\n") {
+            Err(SolverError::Parse { line, error }) => {
+                assert_eq!(line, 3);
+                assert_eq!(error, ParseError::BadLiteral("x".to_string()));
+            }
+            _ => panic!("Bad literal read"),
+        }
+
+        match Formula::from_dimacs("p cnf 3\n1 -2 0\n") {
+            Err(SolverError::Parse { line: 1, .. }) => {}
+            _ => panic!("Bad preface read"),
+        }
+
+        match Formula::from_dimacs("1 -2 0\n1 2") {
+            Err(SolverError::Parse { line: 2, error }) => {
+                assert_eq!(error, ParseError::UnterminatedClause)
+            }
+            _ => panic!("Unterminated clause read"),
+        }
+    }
+
+    #[test]
+    fn knf() {
+        let formula = Formula::from_dimacs("p knf 3 2\nk 2 1 -2 3 0\n1 2 0\n").unwrap();
+        assert_eq!(formula.clause_count(), 1);
+        assert_eq!(formula.cardinality_constraints[0].0, 2);
+    }
+
+    #[test]
+    fn icnf() {
+        let formula = Formula::from_dimacs("p inccnf\n1 2 0\na -1 0\n-2 0\na 1 0\n").unwrap();
+        assert_eq!(formula.clause_count(), 2);
+        let positions = formula.cubes.iter().map(|(position, _)| *position);
+        assert_eq!(positions.collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn wcnf() {
+        let formula = Formula::from_wcnf("p wcnf 2 3 10\n10 1 2 0\n3 -1 0\n2 -2 0\n").unwrap();
+        assert_eq!(formula.clause_count(), 1);
+        assert_eq!(formula.soft_clauses.len(), 2);
+
+        let formula = Formula::from_wcnf("c later\nh 1 2 0\n3 -1 0\n2 -2 0\n").unwrap();
+        assert_eq!(formula.clause_count(), 1);
+        assert_eq!(formula.soft_clauses[0].0, 3);
+    }
+
+    #[test]
+    fn opb() {
+        let formula = Formula::from_opb(
+            "* #variable= 2 #constraint= 1\nmin: +2 x1 -1 x2 ;\n+1 x1 +1 ~x2 >= 1 ;\n",
+            PbEncoding::Bdd,
+        )
+        .unwrap();
+        assert_eq!(formula.soft_clauses.len(), 2);
+        assert_eq!(formula.cost_offset, -1);
+        assert_eq!(formula.clause_count(), 1);
 
-            to += 1;
+        match Formula::from_opb("+1 x1 x2 >= 1 ;\n", PbEncoding::Bdd) {
+            Err(SolverError::Parse {
+                error: ParseError::BadTerm(_),
+                ..
+            }) => {}
+            _ => panic!("Product term read"),
         }
-        the_solve
     }
 }
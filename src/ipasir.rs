@@ -0,0 +1,275 @@
+//! The [IPASIR](https://github.com/biotomas/ipasir) interface to an incremental solve.
+//!
+//! Variables are numbered from 1, and a literal is a variable or the negation of a variable.
+//! When a variable is first seen, the variable and each variable with a lower number is added to the solve, so the variable numbered n is the variable of the solve with id n - 1.
+//!
+//! An error of the solver is returned by [ipasir_solve] as [IPASIR_ERROR], including any error from adding a clause since the last solve.
+
+use std::ffi::{c_char, c_int, c_void};
+
+use crate::errors::SolverError;
+use crate::structures::{
+    clause::{clause_vec::ClauseVec, stored_clause::ClauseSource},
+    formula::Formula,
+    literal::Literal,
+    solve::{
+        callbacks::{LearnCallback, TerminateCallback},
+        Solve, SolveResult,
+    },
+    valuation::Valuation,
+    variable::VariableId,
+};
+
+static SIGNATURE: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"), "\0");
+
+/// The status returned by [ipasir_solve] on an error of the solver
+pub const IPASIR_ERROR: c_int = -1;
+
+pub struct IpasirSolver {
+    the_solve: Solve,
+    /// The literals of the clause being added
+    clause: ClauseVec,
+    /// The assumptions for the next solve
+    assumptions: Vec<Literal>,
+    /// The first error from adding a clause since the last solve
+    error: Option<SolverError>,
+}
+
+/// The data given with a callback, which is only used by the thread which calls the solve.
+struct CallbackData(*mut c_void);
+
+unsafe impl Send for CallbackData {}
+
+impl CallbackData {
+    fn pointer(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+impl IpasirSolver {
+    fn new() -> Result<Self, SolverError> {
+        Ok(IpasirSolver {
+            the_solve: Solve::from_formula(Formula::new())?,
+            clause: vec![],
+            assumptions: vec![],
+            error: None,
+        })
+    }
+
+    /// The literal of the solve for the literal of the interface, adding the variable to the solve if needed.
+    fn literal(&mut self, lit: c_int) -> Literal {
+        let variable = lit.unsigned_abs() as usize;
+        while self.the_solve.variables.len() < variable {
+            let name = (self.the_solve.variables.len() + 1).to_string();
+            self.the_solve.fresh_variable(&name);
+        }
+        Literal::new((variable - 1) as VariableId, lit > 0)
+    }
+
+    /// The literal of the solve for the literal of the interface, if the variable has been seen.
+    fn known_literal(&self, lit: c_int) -> Option<Literal> {
+        let variable = lit.unsigned_abs() as usize;
+        match variable > 0 && variable <= self.the_solve.variables.len() {
+            true => Some(Literal::new((variable - 1) as VariableId, lit > 0)),
+            false => None,
+        }
+    }
+}
+
+/// The literal of the interface for the literal of the solve.
+fn external_literal(literal: Literal) -> c_int {
+    let variable = literal.v_id() as c_int + 1;
+    match literal.polarity() {
+        true => variable,
+        false => -variable,
+    }
+}
+
+/// The name and version of the solver.
+#[no_mangle]
+pub extern "C" fn ipasir_signature() -> *const c_char {
+    SIGNATURE.as_ptr() as *const c_char
+}
+
+/// A new solver, to be released by [ipasir_release], or null if no solver could be made.
+#[no_mangle]
+pub extern "C" fn ipasir_init() -> *mut c_void {
+    match IpasirSolver::new() {
+        Ok(solver) => Box::into_raw(Box::new(solver)) as *mut c_void,
+        Err(e) => {
+            log::error!("Failed to make a solver: {e}");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Releases the solver.
+///
+/// # Safety
+/// The solver must be from [ipasir_init], and must not be used after release.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_release(solver: *mut c_void) {
+    drop(Box::from_raw(solver as *mut IpasirSolver));
+}
+
+/// Adds a literal to the clause being added, or adds the clause to the solver if the literal is 0.
+///
+/// # Safety
+/// The solver must be from [ipasir_init].
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_add(solver: *mut c_void, lit_or_zero: c_int) {
+    let solver = &mut *(solver as *mut IpasirSolver);
+    match lit_or_zero {
+        0 => {
+            let clause = std::mem::take(&mut solver.clause);
+            if let Err(e) = solver.the_solve.add_clause(clause, ClauseSource::Formula) {
+                log::error!("Failed to add a clause: {e}");
+                solver.error.get_or_insert(e);
+            }
+        }
+        lit => {
+            let literal = solver.literal(lit);
+            solver.clause.push(literal);
+        }
+    }
+}
+
+/// Assumes the literal for the next solve.
+///
+/// # Safety
+/// The solver must be from [ipasir_init].
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_assume(solver: *mut c_void, lit: c_int) {
+    let solver = &mut *(solver as *mut IpasirSolver);
+    let literal = solver.literal(lit);
+    solver.assumptions.push(literal);
+}
+
+/// Solves under the assumptions made since the last solve, returning 10 if satisfiable, 20 if unsatisfiable, 0 if the solve was stopped, and [IPASIR_ERROR] on an error.
+///
+/// # Safety
+/// The solver must be from [ipasir_init].
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_solve(solver: *mut c_void) -> c_int {
+    let solver = &mut *(solver as *mut IpasirSolver);
+    let assumptions = std::mem::take(&mut solver.assumptions);
+    if solver.error.take().is_some() {
+        return IPASIR_ERROR;
+    }
+    match solver.the_solve.solve_under_assumptions(&assumptions) {
+        Ok((SolveResult::Satisfiable, _)) => 10,
+        Ok((SolveResult::Unsatisfiable, _)) => 20,
+        Ok((SolveResult::Unknown, _)) => 0,
+        Err(e) => {
+            log::error!("Failed to solve: {e}");
+            IPASIR_ERROR
+        }
+    }
+}
+
+/// After a satisfiable solve, the literal if the literal is true, the negation of the literal if the literal is false, and 0 if the variable has no value.
+///
+/// # Safety
+/// The solver must be from [ipasir_init].
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_val(solver: *mut c_void, lit: c_int) -> c_int {
+    let solver = &*(solver as *mut IpasirSolver);
+    let literal = match solver.known_literal(lit) {
+        Some(literal) => literal,
+        None => return 0,
+    };
+    match solver.the_solve.valuation.of_literal(literal) {
+        Some(true) => lit,
+        Some(false) => -lit,
+        None => 0,
+    }
+}
+
+/// After an unsatisfiable solve, 1 if the assumption of the literal was used to show the solve was unsatisfiable, and 0 otherwise.
+///
+/// # Safety
+/// The solver must be from [ipasir_init].
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_failed(solver: *mut c_void, lit: c_int) -> c_int {
+    let solver = &*(solver as *mut IpasirSolver);
+    match solver.known_literal(lit) {
+        Some(literal) => solver.the_solve.failed_assumptions().contains(&literal) as c_int,
+        None => 0,
+    }
+}
+
+/// Sets a callback which is asked on occasion during a solve whether to stop, with a non-zero return stopping the solve.
+///
+/// # Safety
+/// The solver must be from [ipasir_init], and the callback must accept the data for as long as the callback is set.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_set_terminate(
+    solver: *mut c_void,
+    data: *mut c_void,
+    terminate: Option<extern "C" fn(data: *mut c_void) -> c_int>,
+) {
+    let solver = &mut *(solver as *mut IpasirSolver);
+    let data = CallbackData(data);
+    let callback = terminate
+        .map(|terminate| Box::new(move || terminate(data.pointer()) != 0) as TerminateCallback);
+    solver.the_solve.set_terminate(callback);
+}
+
+/// Sets a callback given each learnt clause of at most the given length, as a zero terminated array of literals.
+///
+/// # Safety
+/// The solver must be from [ipasir_init], and the callback must accept the data for as long as the callback is set.
+#[no_mangle]
+pub unsafe extern "C" fn ipasir_set_learn(
+    solver: *mut c_void,
+    data: *mut c_void,
+    max_length: c_int,
+    learn: Option<extern "C" fn(data: *mut c_void, clause: *mut c_int)>,
+) {
+    let solver = &mut *(solver as *mut IpasirSolver);
+    let data = CallbackData(data);
+    let callback = learn.map(|learn| {
+        Box::new(move |clause: &[Literal], _lbd: usize| {
+            let mut lits = clause
+                .iter()
+                .map(|literal| external_literal(*literal))
+                .collect::<Vec<_>>();
+            lits.push(0);
+            learn(data.pointer(), lits.as_mut_ptr());
+        }) as LearnCallback
+    });
+    solver
+        .the_solve
+        .set_learn(max_length.max(0) as usize, usize::MAX, callback);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_interface() {
+        unsafe {
+            let solver = ipasir_init();
+            for lit in [1, 2, 0, -1, 2, 0] {
+                ipasir_add(solver, lit);
+            }
+            assert_eq!(ipasir_solve(solver), 10);
+            assert_eq!(ipasir_val(solver, 2), 2);
+
+            ipasir_assume(solver, -2);
+            assert_eq!(ipasir_solve(solver), 20);
+            assert_eq!(ipasir_failed(solver, -2), 1);
+
+            ipasir_assume(solver, 1);
+            assert_eq!(ipasir_solve(solver), 10);
+            assert_eq!(ipasir_val(solver, -1), 1);
+
+            // an error from adding a clause is returned by the next solve only
+            (*(solver as *mut IpasirSolver)).error = Some(SolverError::EmptyClause);
+            assert_eq!(ipasir_solve(solver), IPASIR_ERROR);
+            assert_eq!(ipasir_solve(solver), 10);
+            ipasir_release(solver);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+#![allow(dead_code)]
+
+pub mod errors;
+pub mod io;
+pub mod ipasir;
+pub mod procedures;
+pub mod structures;
@@ -1,14 +1,20 @@
-#![allow(dead_code)]
-
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use std::fs;
-use structures::solve::config::{ExplorationPriority, StoppingCriteria};
-mod io;
-mod procedures;
-mod structures;
+use std::io::Write;
 
-use crate::structures::formula::Formula;
-use crate::structures::solve::{config, Solve, SolveResult};
+use otter_sat::errors::SolverError;
+use otter_sat::structures::clause::{clause_vec::ClauseVec, stored_clause::ClauseSource, Clause};
+use otter_sat::structures::formula::Formula;
+use otter_sat::structures::literal::Literal;
+use otter_sat::structures::pseudo_boolean::PbEncoding;
+use otter_sat::structures::solve::backbone::BackboneResult;
+use otter_sat::structures::solve::config::{ExplorationPriority, StoppingCriteria};
+use otter_sat::structures::solve::maxsat::MaxSatResult;
+use otter_sat::structures::solve::mus::MusResult;
+use otter_sat::structures::solve::portfolio::solve_portfolio;
+use otter_sat::structures::solve::{config, Solve, SolveResult};
+use otter_sat::structures::valuation::Valuation;
+use otter_sat::structures::variable::VariableId;
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -26,21 +32,33 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     assignment: bool,
 
-    /// Display an unsatisfiable core on UNSAT
+    /// Display an unsatisfiable core on UNSAT, as the positions of clauses of the formula from 1
     #[arg(short, long, default_value_t = false)]
     core: bool,
 
+    /// Write an unsatisfiable core on UNSAT to the given file, as CNF, or with `--mus` the minimal unsatisfiable subset
+    #[arg(long)]
+    core_file: Option<std::path::PathBuf>,
+
+    /// Find a minimal unsatisfiable subset of the clauses, given as the positions of the clauses from 1
+    #[arg(long, default_value_t = false)]
+    mus: bool,
+
+    /// Find the literals true in every model of the formula
+    #[arg(long, default_value_t = false)]
+    backbone: bool,
+
     /// Required glue strength
     #[arg(short, long, default_value_t = 2)]
     glue_strength: usize,
 
     /// Resolution stopping criteria
-    #[arg(long, default_value_t = String::from("FirstUIP"))]
-    stopping_criteria: String,
+    #[arg(long, default_value = "FirstUIP", value_parser = stopping_criteria)]
+    stopping_criteria: StoppingCriteria,
 
     /// Suggest priority exploring conflcits, implications, or take no interest
-    #[arg(long, default_value_t = String::from("Default"))]
-    exploration_priority: String,
+    #[arg(long, default_value = "Default", value_parser = exploration_priority)]
+    exploration_priority: ExplorationPriority,
 
     /// Allow for the clauses to be forgotten, on occassion
     #[arg(long, default_value_t = false)]
@@ -54,9 +72,154 @@ struct Args {
     #[arg(long, default_value_t = false)]
     hobson: bool,
 
+    /// Solve by CDCL, or by local search alone with `sls`, which does not end on an unsatisfiable formula without a time limit
+    #[arg(long, value_enum, default_value = "cdcl")]
+    mode: Mode,
+
+    /// Search for phases by local search after each given count of restarts
+    #[arg(long)]
+    local_search: Option<usize>,
+
+    /// Backtrack a single level in place of any backjump over more than the given count of levels (default: 100)
+    #[arg(long, num_args = 0..=1, default_missing_value = "100")]
+    chrono: Option<usize>,
+
+    /// On a restart, keep each level with a choice which would be made again
+    #[arg(long, default_value_t = false)]
+    reuse_trail: bool,
+
     /// Time limit for the solve
     #[arg(short, long, value_parser = |seconds: &str| seconds.parse().map(std::time::Duration::from_secs))]
     time: Option<std::time::Duration>,
+
+    /// Enumerate models, up to the given count if given
+    #[arg(long, num_args = 0..=1)]
+    enumerate: Option<Option<usize>>,
+
+    /// The variables to enumerate models over, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    projection: Option<Vec<String>>,
+
+    /// Block each enumerated model by the choices made for the model
+    #[arg(long, default_value_t = false)]
+    decision_blocking: bool,
+
+    /// Count models, projected onto any variables of `c p show` lines
+    #[arg(long, default_value_t = false)]
+    count: bool,
+
+    /// Read the formula as WCNF and find a model of least cost, implied by a `.wcnf` file
+    #[arg(long, default_value_t = false)]
+    maxsat: bool,
+
+    /// Read the formula as OPB, minimising any objective, implied by a `.opb` file
+    #[arg(long, default_value_t = false)]
+    opb: bool,
+
+    /// Encode pseudo-Boolean constraints with a sequential counter, totalizer, or BDD
+    #[arg(long, default_value = "totalizer", value_parser = pb_encoding)]
+    pb_encoding: PbEncoding,
+
+    /// Recover XOR constraints over at most the given count of variables (default: 5, at most 32) from the clauses
+    #[arg(long, num_args = 0..=1, default_missing_value = "5")]
+    recover_xors: Option<usize>,
+
+    /// Split the formula into cubes of at most the given count of literals, written as iCNF
+    #[arg(long)]
+    cube: Option<usize>,
+
+    /// Solve the formula under each cube of the `a` lines of an iCNF file
+    #[arg(long, default_value_t = false)]
+    solve_cubes: bool,
+
+    /// Read the formula as iCNF and solve each query of the `a` lines in turn, implied by a `.icnf` file
+    #[arg(long, default_value_t = false)]
+    incremental: bool,
+
+    /// Solve with a portfolio of differently configured solves on the given count of threads, which excludes modes other than a plain solve
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Mode {
+    #[value(alias = "CDCL")]
+    Cdcl,
+    #[value(alias = "SLS")]
+    Sls,
+}
+
+fn stopping_criteria(name: &str) -> Result<StoppingCriteria, String> {
+    match name {
+        "FirstUIP" | "firstUIP" | "1UIP" | "1uip" => Ok(StoppingCriteria::FirstAssertingUIP),
+        "None" | "none" => Ok(StoppingCriteria::None),
+        _ => Err(format!("unknown stopping criteria '{name}'")),
+    }
+}
+
+fn exploration_priority(name: &str) -> Result<ExplorationPriority, String> {
+    match name {
+        "Implication" | "implication" | "imp" => Ok(ExplorationPriority::Implication),
+        "Conflict" | "conflict" | "conf" => Ok(ExplorationPriority::Conflict),
+        "Default" | "default" => Ok(ExplorationPriority::Default),
+        _ => Err(format!("unknown exploration priority '{name}'")),
+    }
+}
+
+fn pb_encoding(name: &str) -> Result<PbEncoding, String> {
+    match name {
+        "SequentialCounter" | "sequential" | "seq" => Ok(PbEncoding::SequentialCounter),
+        "Totalizer" | "totalizer" | "tot" => Ok(PbEncoding::Totalizer),
+        "Bdd" | "BDD" | "bdd" => Ok(PbEncoding::Bdd),
+        _ => Err(format!("unknown pseudo-Boolean encoding '{name}'")),
+    }
+}
+
+impl Args {
+    fn maxsat(&self) -> bool {
+        self.maxsat
+            || self
+                .formula_file
+                .extension()
+                .is_some_and(|extension| extension == "wcnf")
+    }
+
+    fn incremental(&self) -> bool {
+        self.incremental
+            || (!self.solve_cubes
+                && self
+                    .formula_file
+                    .extension()
+                    .is_some_and(|extension| extension == "icnf"))
+    }
+
+    /// The flag of some mode which requires a single solve, and so cannot be used with a portfolio.
+    fn single_solve_mode(&self) -> Option<&'static str> {
+        [
+            (self.core, "--core"),
+            (self.core_file.is_some(), "--core-file"),
+            (self.mus, "--mus"),
+            (self.backbone, "--backbone"),
+            (matches!(self.mode, Mode::Sls), "--mode sls"),
+            (self.enumerate.is_some(), "--enumerate"),
+            (self.count, "--count"),
+            (self.maxsat(), "--maxsat"),
+            (self.opb(), "--opb"),
+            (self.incremental(), "--incremental"),
+            (self.cube.is_some(), "--cube"),
+            (self.solve_cubes, "--solve-cubes"),
+        ]
+        .into_iter()
+        .find_map(|(set, flag)| set.then_some(flag))
+    }
+
+    fn opb(&self) -> bool {
+        self.opb
+            || self
+                .formula_file
+                .extension()
+                .is_some_and(|extension| extension == "opb")
+    }
 }
 
 #[rustfmt::skip]
@@ -67,24 +230,20 @@ fn main() {
     }
 
     let args = Args::parse();
+    if let (2.., Some(flag)) = (args.threads, args.single_solve_mode()) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, format!("--threads cannot be used with {flag}"))
+            .exit();
+    }
 
     // set up the configuration, unsafely as global variables are used
     // see the config file for access procedures
     unsafe {
         config::GLUE_STRENGTH = args.glue_strength;
         config::SHOW_STATS = args.stats;
-        config::EXPLORATION_PRIORITY = match args.exploration_priority.as_str() {
-            "Implication" | "implication" | "imp" => ExplorationPriority::Implication,
-            "Conflict" | "conflict" | "conf" => ExplorationPriority::Conflict,
-            "Default" | "default" => ExplorationPriority::Default,
-            _ => panic!("Unknown conflict priority"),
-        };
-        config::STOPPING_CRITERIA = match args.stopping_criteria.as_str() {
-            "FirstUIP" | "firstUIP" | "1UIP" | "1uip" => StoppingCriteria::FirstAssertingUIP,
-            "None" | "none" => StoppingCriteria::None,
-            _ => panic!("Unknown stopping critera"),
-        };
-        config::SHOW_CORE = args.core;
+        config::EXPLORATION_PRIORITY = args.exploration_priority.clone();
+        config::STOPPING_CRITERIA = args.stopping_criteria;
+        config::SHOW_CORE = args.core || args.core_file.is_some();
         config::SHOW_ASSIGNMENT = args.assignment;
         config::RESTARTS_ALLOWED = args.restarts;
         config::REDUCTION_ALLOWED =
@@ -95,42 +254,486 @@ fn main() {
                 args.reduction
             };
         config::TIME_LIMIT = args.time;
+        config::CHRONOLOGICAL_BACKTRACKING = args.chrono;
+        config::TRAIL_REUSE = args.reuse_trail;
+        config::LOCAL_SEARCH =
+            if args.local_search.is_some() && !args.restarts {
+                println!("c LOCAL SEARCH REQUIRES RESTARTS TO BE ENABLED");
+                None
+            } else {
+                args.local_search
+            };
     }
 
     if let Ok(contents) = fs::read_to_string(&args.formula_file) {
-        let formula = Formula::from_dimacs(&contents);
+        match solve_formula(&args, &contents) {
+            Ok(result) => {
+                // a count, optimum, subset, or backbone is reported with the count, optimum, subset, or backbone, each query of iCNF is reported on its own, and cubes are written as iCNF
+                if !args.count && !args.maxsat() && !args.opb() && !args.incremental() && args.cube.is_none() && !args.mus && !args.backbone {
+                    match result {
+                        SolveResult::Unsatisfiable => println!("s UNSATISFIABLE"),
+                        SolveResult::Satisfiable => println!("s SATISFIABLE"),
+                        SolveResult::Unknown => println!("s UNKNOWN"),
+                    }
+                }
+                std::process::exit(match result {
+                    SolveResult::Unsatisfiable => 00,
+                    SolveResult::Satisfiable => 10,
+                    SolveResult::Unknown => 20,
+                });
+            }
+            Err(e) => {
+                println!("c ERROR: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        println!("Error reading file")
+    }
+}
+
+#[rustfmt::skip]
+fn solve_formula(args: &Args, contents: &str) -> Result<SolveResult, SolverError> {
+    if args.maxsat() {
+        return maxsat(contents);
+    }
+    if args.opb() {
+        return pseudo_boolean(contents, args.pb_encoding);
+    }
+    if args.incremental() {
+        return incremental(contents, &mut std::io::stdout());
+    }
 
+    let mut formula = Formula::from_dimacs(contents)?;
+
+    if unsafe { config::SHOW_STATS } {
+        println!("c 🦦");
+        println!("c Parsing formula from file: {:?}", args.formula_file);
+        println!("c Parsed formula with {} variables and {} clauses", formula.variable_count(), formula.clause_count());
+        if let Some(limit) = unsafe { config::TIME_LIMIT } {
+            println!("c TIME LIMIT: {:.2?}", limit);
+        }
+    }
+    if args.mus {
+        return mus(args, formula);
+    }
+    // models are of every variable of the preface, including any variable which occurs in no clause
+    if args.count || args.enumerate.is_some() {
+        formula.add_preface_variables();
+    }
+    if let Some(max_size) = args.recover_xors {
+        let recovered = formula.recover_xors(max_size);
         if unsafe { config::SHOW_STATS } {
-            println!("c 🦦");
-            println!("c Parsing formula from file: {:?}", args.formula_file);
-            println!("c Parsed formula with {} variables and {} clauses", formula.variable_count(), formula.clause_count());
-            if let Some(limit) = unsafe { config::TIME_LIMIT } {
-                println!("c TIME LIMIT: {:.2?}", limit);
+            println!("c Recovered {recovered} XOR constraints");
+        }
+    }
+    log::trace!("Formula processed");
+    let projection = formula.projection.clone();
+
+    if let Some(depth) = args.cube {
+        return cube(contents, Solve::from_formula(formula)?, depth);
+    }
+
+    if args.solve_cubes {
+        let cubes = std::mem::take(&mut formula.cubes)
+            .into_iter()
+            .map(|(_, cube)| cube)
+            .collect::<Vec<_>>();
+        let mut the_solve = Solve::from_formula(formula)?;
+        let (result, _) = the_solve.solve_cubes(&cubes)?;
+        return Ok(result);
+    }
+
+    if args.threads > 1 {
+        let (result, _) = solve_portfolio(formula, args.threads)?;
+        return Ok(result);
+    }
+
+    // the clauses of the formula are kept to write any core
+    let original = unsafe { config::SHOW_CORE }.then(|| formula.clone());
+
+    let mut the_solve = Solve::from_formula(formula)?;
+    log::trace!("Solve initialised");
+
+    if let Mode::Sls = args.mode {
+        return local_search(the_solve);
+    }
+
+    if let Some(limit) = args.enumerate {
+        return enumerate(args, the_solve, limit);
+    }
+
+    if args.backbone {
+        return backbone(the_solve);
+    }
+
+    if args.count {
+        let count_type = if projection.is_some() { "pmc" } else { "mc" };
+        let count = the_solve.count_models(projection.clone())?;
+        println!("s mc {count}");
+        println!("c s type {count_type}");
+        println!("c s log10-estimate {:.6}", count.log10());
+        println!("c s exact arb int {count}");
+        return match count.is_zero() {
+            true => Ok(SolveResult::Unsatisfiable),
+            false => Ok(SolveResult::Satisfiable),
+        };
+    }
+
+    let (result, stats) = the_solve.do_solve()?;
+    if unsafe { config::SHOW_STATS } {
+        println!("{stats}");
+    }
+    if let (SolveResult::Unsatisfiable, Some(formula)) = (&result, &original) {
+        core(args, &the_solve, formula);
+    }
+    Ok(result)
+}
+
+/// Solves by local search alone, with any model found by a search which follows the phases of the local search.
+fn local_search(mut the_solve: Solve) -> Result<SolveResult, SolverError> {
+    let result = the_solve.local_search(usize::MAX)?;
+    if unsafe { config::SHOW_STATS } {
+        println!(
+            "c LOCAL SEARCH: {}",
+            match result {
+                SolveResult::Satisfiable => "MODEL FOUND",
+                SolveResult::Unsatisfiable => "UNSATISFIABLE AT LEVEL 0",
+                SolveResult::Unknown => "NO MODEL FOUND",
             }
+        );
+    }
+    match result {
+        SolveResult::Satisfiable => Ok(the_solve.do_solve()?.0),
+        result => Ok(result),
+    }
+}
+
+/// Finds the backbone of the formula, given on a `v` line.
+fn backbone(mut the_solve: Solve) -> Result<SolveResult, SolverError> {
+    match the_solve.backbone()? {
+        BackboneResult::Backbone(backbone) => {
+            println!("s SATISFIABLE");
+            println!("c BACKBONE SIZE: {}", backbone.len());
+            println!("v {}", backbone.as_dimacs(&the_solve.variables));
+            Ok(SolveResult::Satisfiable)
         }
-        log::trace!("Formula processed");
-        let mut the_solve = Solve::from_formula(formula);
-        log::trace!("Solve initialised");
+        BackboneResult::Unsatisfiable => {
+            println!("s UNSATISFIABLE");
+            Ok(SolveResult::Unsatisfiable)
+        }
+        BackboneResult::Unknown => {
+            println!("s UNKNOWN");
+            Ok(SolveResult::Unknown)
+        }
+    }
+}
 
-        let (result, stats) = the_solve.do_solve();
-        if unsafe { config::SHOW_STATS } {
-            println!("{stats}");
+/// Finds a minimal unsatisfiable subset of the clauses of the formula, with any other constraint of the formula kept.
+fn mus(args: &Args, mut formula: Formula) -> Result<SolveResult, SolverError> {
+    let clauses = std::mem::take(&mut formula.clauses);
+    let mut the_solve = Solve::from_formula(formula.clone())?;
+
+    match the_solve.minimal_unsatisfiable_subset(clauses.clone())? {
+        MusResult::Minimal(subset) => {
+            let positions = subset.iter().map(|index| (index + 1).to_string());
+            println!("s UNSATISFIABLE");
+            println!("c MUS SIZE: {}", subset.len());
+            println!("v {} 0", positions.collect::<Vec<_>>().join(" "));
+            if let Some(path) = &args.core_file {
+                formula.clauses = clauses;
+                let subset = subset.iter().map(|index| *index as u32).collect::<Vec<_>>();
+                write_core(path, &formula, &subset);
+            }
+            Ok(SolveResult::Unsatisfiable)
         }
-        match result {
-            SolveResult::Unsatisfiable => {
-                println!("s UNSATISFIABLE");
-                std::process::exit(00);
+        MusResult::Satisfiable => {
+            println!("s SATISFIABLE");
+            Ok(SolveResult::Satisfiable)
+        }
+        MusResult::Unknown => {
+            println!("s UNKNOWN");
+            Ok(SolveResult::Unknown)
+        }
+    }
+}
+
+/// Displays the unsatisfiable core of the solve as the positions of the clauses of the formula, and writes the core as CNF if a core file is given.
+fn core(args: &Args, the_solve: &Solve, formula: &Formula) {
+    let Some(core) = the_solve.unsat_core() else {
+        println!("c NO CORE");
+        return;
+    };
+    if args.core {
+        let positions = core.iter().map(|index| (index + 1).to_string());
+        println!("c CORE: {}", positions.collect::<Vec<_>>().join(" "));
+    }
+
+    if let Some(path) = &args.core_file {
+        write_core(path, formula, core);
+    }
+}
+
+/// Writes the clauses of the formula with the given original indices as CNF.
+///
+/// Any cardinality constraint is written as a KNF line.
+fn write_core(path: &std::path::Path, formula: &Formula, core: &[u32]) {
+    let variable_count = formula
+        .preface_variable_count
+        .unwrap_or(formula.variable_count());
+    let mut cnf = format!("p cnf {variable_count} {}\n", core.len());
+    for index in core.iter().map(|index| *index as usize) {
+        match formula.clauses.get(index) {
+            Some(clause) => cnf.push_str(&clause.as_dimacs(&formula.variables)),
+            None => {
+                let (degree, literals) =
+                    &formula.cardinality_constraints[index - formula.clauses.len()];
+                cnf.push_str(&format!(
+                    "k {degree} {}",
+                    literals.as_dimacs(&formula.variables)
+                ));
+            }
+        }
+        cnf.push('\n');
+    }
+    if let Err(e) = fs::write(path, cnf) {
+        println!("c ERROR: Failed to write core: {e}");
+    }
+}
+
+fn maxsat(contents: &str) -> Result<SolveResult, SolverError> {
+    let mut formula = Formula::from_wcnf(contents)?;
+    let soft_clauses = std::mem::take(&mut formula.soft_clauses);
+    let preface_variable_count = formula.preface_variable_count;
+    let mut the_solve = Solve::from_formula(formula)?;
+
+    match the_solve.minimise(soft_clauses)? {
+        MaxSatResult::Optimum(cost) => {
+            // variables of the input are named by number, and any other variable was added by the solve
+            let named = the_solve
+                .variables
+                .iter()
+                .filter_map(|variable| {
+                    Some((variable.name().parse::<usize>().ok()?, variable.id()))
+                })
+                .collect::<Vec<_>>();
+            let width = preface_variable_count
+                .or(named.iter().map(|(name, _)| *name).max())
+                .unwrap_or(0);
+            let mut values = vec!['0'; width];
+            for (name, v_id) in named {
+                if name <= width && the_solve.valuation.of_v_id(v_id) == Some(true) {
+                    values[name - 1] = '1';
+                }
             }
+            println!("o {cost}");
+            println!("s OPTIMUM FOUND");
+            println!("v {}", values.iter().collect::<String>());
+            Ok(SolveResult::Satisfiable)
+        }
+        MaxSatResult::Unsatisfiable => {
+            println!("s UNSATISFIABLE");
+            Ok(SolveResult::Unsatisfiable)
+        }
+        MaxSatResult::Unknown => {
+            println!("s UNKNOWN");
+            Ok(SolveResult::Unknown)
+        }
+    }
+}
+
+fn pseudo_boolean(contents: &str, encoding: PbEncoding) -> Result<SolveResult, SolverError> {
+    let mut formula = Formula::from_opb(contents, encoding)?;
+    let soft_clauses = std::mem::take(&mut formula.soft_clauses);
+    let cost_offset = formula.cost_offset;
+    let mut the_solve = Solve::from_formula(formula)?;
+
+    // variables added by encodings or relaxation are named with a leading underscore
+    let model = |the_solve: &Solve| {
+        the_solve
+            .variables
+            .iter()
+            .filter(|variable| !variable.name().starts_with('_'))
+            .map(
+                |variable| match the_solve.valuation.of_v_id(variable.id()) {
+                    Some(true) => variable.name().to_string(),
+                    _ => format!("-{}", variable.name()),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    if soft_clauses.is_empty() {
+        let (result, _) = the_solve.do_solve()?;
+        match result {
             SolveResult::Satisfiable => {
                 println!("s SATISFIABLE");
-                std::process::exit(10);
+                println!("v {}", model(&the_solve));
+            }
+            SolveResult::Unsatisfiable => println!("s UNSATISFIABLE"),
+            SolveResult::Unknown => println!("s UNKNOWN"),
+        }
+        return Ok(result);
+    }
+
+    match the_solve.minimise(soft_clauses)? {
+        MaxSatResult::Optimum(cost) => {
+            println!("o {}", cost as i64 + cost_offset);
+            println!("s OPTIMUM FOUND");
+            println!("v {}", model(&the_solve));
+            Ok(SolveResult::Satisfiable)
+        }
+        MaxSatResult::Unsatisfiable => {
+            println!("s UNSATISFIABLE");
+            Ok(SolveResult::Unsatisfiable)
+        }
+        MaxSatResult::Unknown => {
+            println!("s UNKNOWN");
+            Ok(SolveResult::Unknown)
+        }
+    }
+}
+
+fn enumerate(
+    args: &Args,
+    mut the_solve: Solve,
+    limit: Option<usize>,
+) -> Result<SolveResult, SolverError> {
+    let names = the_solve
+        .variables
+        .iter()
+        .map(|variable| variable.name().to_string())
+        .collect::<Vec<_>>();
+
+    let projection = match &args.projection {
+        Some(projection_names) => Some(
+            projection_names
+                .iter()
+                .map(|name| match names.iter().position(|n| n == name) {
+                    Some(index) => Ok(index as VariableId),
+                    None => Err(SolverError::UnknownVariable(name.clone())),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        None => None,
+    };
+
+    let mut models = the_solve.models(projection, args.decision_blocking);
+    let mut count = 0;
+    for model in models.by_ref().take(limit.unwrap_or(usize::MAX)) {
+        count += 1;
+        let model_string = model?
+            .iter()
+            .map(|literal| match literal.polarity() {
+                true => names[literal.v_id() as usize].clone(),
+                false => format!("-{}", names[literal.v_id() as usize]),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("c MODEL {count}: {model_string}");
+    }
+    println!("c MODELS: {count}");
+
+    if count > 0 {
+        Ok(SolveResult::Satisfiable)
+    } else if models.exhausted() {
+        Ok(SolveResult::Unsatisfiable)
+    } else {
+        Ok(SolveResult::Unknown)
+    }
+}
+
+/// Solves each query of an iCNF formula in turn, with the clauses read before the query.
+///
+/// For each query, the result is written followed by a model on a `v` line or the failed assumptions on an `f` line.
+/// A formula without any query is solved as a single query without assumptions.
+fn incremental(contents: &str, out: &mut impl Write) -> Result<SolveResult, SolverError> {
+    let mut formula = Formula::from_dimacs(contents)?;
+    let mut queries = std::mem::take(&mut formula.cubes);
+    let clause_count = formula.clause_count();
+    if queries.is_empty() {
+        queries.push((clause_count, vec![]));
+    }
+    let mut clauses = std::mem::take(&mut formula.clauses).into_iter();
+    let mut the_solve = Solve::from_formula(formula)?;
+
+    let mut added = 0;
+    let mut result = SolveResult::Unknown;
+    for (position, assumptions) in queries {
+        for clause in clauses.by_ref().take(position - added) {
+            the_solve.add_clause(clause, ClauseSource::Formula)?;
+        }
+        added = position;
+
+        result = the_solve.solve_under_assumptions(&assumptions)?.0;
+        match result {
+            SolveResult::Satisfiable => {
+                let model = the_solve
+                    .valuation
+                    .values()
+                    .enumerate()
+                    .map(|(v_id, value)| Literal::new(v_id as VariableId, value.unwrap_or(false)))
+                    .collect::<ClauseVec>();
+                let _ = writeln!(out, "s SATISFIABLE");
+                let _ = writeln!(out, "v {}", model.as_dimacs(&the_solve.variables));
+            }
+            SolveResult::Unsatisfiable => {
+                let failed = the_solve.failed_assumptions().to_vec();
+                let _ = writeln!(out, "s UNSATISFIABLE");
+                let _ = writeln!(out, "f {}", failed.as_dimacs(&the_solve.variables));
             }
             SolveResult::Unknown => {
-                println!("s UNKNOWN");
-                std::process::exit(20);
+                let _ = writeln!(out, "s UNKNOWN");
             }
         }
-    } else {
-        println!("Error reading file")
+    }
+    Ok(result)
+}
+
+/// Writes the formula together with the cubes of a split of the formula as iCNF.
+fn cube(contents: &str, mut the_solve: Solve, depth: usize) -> Result<SolveResult, SolverError> {
+    let cubes = the_solve.cubes(depth)?;
+
+    println!("p inccnf");
+    for line in contents.lines().map(str::trim) {
+        match line.chars().next() {
+            Some('%') => break,
+            Some('p') | Some('a') | None => {}
+            Some(_) => println!("{line}"),
+        }
+    }
+    for cube in &cubes {
+        println!("a {}", cube.as_dimacs(&the_solve.variables));
+    }
+    println!("c CUBES: {}", cubes.len());
+
+    match cubes.is_empty() {
+        true => Ok(SolveResult::Unsatisfiable),
+        false => Ok(SolveResult::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_queries() {
+        // each query is of the clauses read before the query
+        let contents = "p inccnf\n1 2 0\na -1 0\n-2 0\na -1 0\na 1 0\n";
+        let mut out = vec![];
+        let result = incremental(contents, &mut out).unwrap();
+        assert!(matches!(result, SolveResult::Satisfiable));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "s SATISFIABLE\nv -1 2 0\ns UNSATISFIABLE\nf -1 0\ns SATISFIABLE\nv 1 -2 0\n"
+        );
+
+        // without a query the formula is solved once
+        let mut out = vec![];
+        let result = incremental("1 0\n-1 0\n", &mut out).unwrap();
+        assert!(matches!(result, SolveResult::Unsatisfiable));
+        assert_eq!(String::from_utf8(out).unwrap(), "s UNSATISFIABLE\nf 0\n");
     }
 }
@@ -2,6 +2,8 @@ pub mod clause;
 pub mod formula;
 pub mod level;
 pub mod literal;
+pub mod model_count;
+pub mod pseudo_boolean;
 pub mod solve;
 pub mod valuation;
 pub mod variable;
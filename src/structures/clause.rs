@@ -2,6 +2,7 @@ pub mod clause_vec;
 pub mod stored_clause;
 
 use crate::structures::{
+    level::LevelIndex,
     literal::Literal,
     valuation::{Valuation, ValuationVec},
     variable::{Variable, VariableId},
@@ -34,7 +35,7 @@ pub trait Clause {
 
     fn asserts(&self, val: &impl Valuation) -> Option<Literal>;
 
-    fn lbd(&self, variables: &[Variable]) -> usize;
+    fn lbd(&self, decision_levels: &[Option<LevelIndex>]) -> usize;
 
     fn find_literal_by_id(&self, id: VariableId) -> Option<Literal>;
 }
@@ -1,5 +1,6 @@
 use crate::structures::{
     clause::Clause,
+    level::LevelIndex,
     literal::Literal,
     valuation::{Valuation, ValuationVec},
     variable::{Variable, VariableId},
@@ -13,30 +14,23 @@ impl Clause for ClauseVec {
     }
 
     fn variables(&self) -> impl Iterator<Item = VariableId> {
-        self.iter().map(|literal| literal.v_id)
+        self.iter().map(|literal| literal.v_id())
     }
 
     fn is_sat_on(&self, valuation: &ValuationVec) -> bool {
-        self.iter()
-            .any(|l| valuation.of_v_id(l.v_id) == Some(l.polarity))
+        self.iter().any(|l| valuation.of_literal(*l) == Some(true))
     }
 
     fn is_unsat_on(&self, valuation: &ValuationVec) -> bool {
-        self.iter().all(|l| {
-            if let Some(var_valuie) = valuation.of_v_id(l.v_id) {
-                var_valuie != l.polarity
-            } else {
-                false
-            }
-        })
+        self.iter().all(|l| valuation.of_literal(*l) == Some(false))
     }
 
     fn find_unit_literal<T: Valuation>(&self, valuation: &T) -> Option<Literal> {
         let mut unit = None;
 
         for literal in self {
-            let assigned_value = valuation.of_v_id(literal.v_id);
-            if assigned_value.is_some_and(|v| v == literal.polarity) {
+            let assigned_value = valuation.of_literal(*literal);
+            if assigned_value == Some(true) {
                 // the clause is satisfied and so does not provide any new information
                 break;
             } else if assigned_value.is_some() {
@@ -61,11 +55,11 @@ impl Clause for ClauseVec {
         let mut the_literals = vec![];
 
         for literal in self {
-            match valuation.of_v_id(literal.v_id) {
-                Some(value) if value == literal.polarity => {
+            match valuation.of_literal(*literal) {
+                Some(true) => {
                     return None;
                 }
-                Some(_value) => continue,
+                Some(false) => continue,
 
                 None => the_literals.push(*literal),
             }
@@ -85,9 +79,9 @@ impl Clause for ClauseVec {
     fn as_dimacs(&self, variables: &[Variable]) -> String {
         let mut the_string = String::from("");
         for literal in self {
-            let the_represenetation = match literal.polarity {
-                true => format!("{} ", variables[literal.v_id()].name()),
-                false => format!("-{} ", variables[literal.v_id()].name()),
+            let the_represenetation = match literal.polarity() {
+                true => format!("{} ", variables[literal.v_id() as usize].name()),
+                false => format!("-{} ", variables[literal.v_id() as usize].name()),
             };
             the_string.push_str(the_represenetation.as_str())
         }
@@ -107,8 +101,8 @@ impl Clause for ClauseVec {
     fn asserts(&self, val: &impl Valuation) -> Option<Literal> {
         let mut the_literal = None;
         for lit in self.literals() {
-            if let Some(existing_val) = val.of_v_id(lit.v_id) {
-                match existing_val == lit.polarity {
+            if let Some(existing_val) = val.of_literal(lit) {
+                match existing_val {
                     true => return None,
                     false => continue,
                 }
@@ -123,14 +117,14 @@ impl Clause for ClauseVec {
 
     // TODO: consider a different approach to lbd
     // e.g. an approximate measure of =2, =3, >4 can be settled much more easily
-    fn lbd(&self, vars: &[Variable]) -> usize {
-        let mut decision_levels = self
+    fn lbd(&self, decision_levels: &[Option<LevelIndex>]) -> usize {
+        let mut levels = self
             .iter()
-            .map(|l| vars[l.v_id()].decision_level())
+            .map(|l| decision_levels[l.v_id() as usize])
             .collect::<Vec<_>>();
-        decision_levels.sort_unstable();
-        decision_levels.dedup();
-        decision_levels.len()
+        levels.sort_unstable();
+        levels.dedup();
+        levels.len()
     }
 
     fn is_empty(&self) -> bool {
@@ -141,7 +135,7 @@ impl Clause for ClauseVec {
     /// Uses binary search on longer clauses, as literals are ordered by variable ids
     fn find_literal_by_id(&self, id: VariableId) -> Option<Literal> {
         if self.len() < 64 {
-            self.iter().find(|l| l.v_id == id).copied()
+            self.iter().find(|l| l.v_id() == id).copied()
         } else {
             find_literal_by_id_binary(self, id)
         }
@@ -157,12 +151,12 @@ fn find_literal_by_id_binary(clause: &[Literal], id: VariableId) -> Option<Liter
         midpoint = min + ((max - min) / 2);
         attempt = clause[midpoint];
         if max - min == 0 {
-            match attempt.v_id == id {
+            match attempt.v_id() == id {
                 true => return Some(attempt),
                 false => return None,
             }
         }
-        match attempt.v_id.cmp(&id) {
+        match attempt.v_id().cmp(&id) {
             std::cmp::Ordering::Less => min = midpoint + 1,
             std::cmp::Ordering::Equal => {
                 return Some(attempt);
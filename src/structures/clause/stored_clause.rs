@@ -1,37 +1,28 @@
 use crate::structures::{
     clause::{Clause, ClauseVec},
+    level::LevelIndex,
     literal::Literal,
     solve::ClauseKey,
     valuation::{Valuation, ValuationVec},
     variable::{Variable, VariableId},
 };
 
-use std::cell::UnsafeCell;
-
-pub struct StoredClause {
+/// A view of some clause in the clause store.
+/// The literals are ordered so the first two literals are the watched literals of the clause.
+pub struct StoredClause<'store> {
     key: ClauseKey,
-    lbd: UnsafeCell<usize>,
-    source: ClauseSource,
-    clause: ClauseVec,
-    the_wc: ClauseVec,
-    cached_watches: (Literal, Literal),
+    lbd: u32,
+    origins: &'store [u32],
+    literals: &'store [u32],
 }
 
 // { Clause enums
 
-#[derive(Debug)]
-pub enum ClauseStatus {
-    Satisfied,        // some watch literal matches
-    Conflict,         // no watch literals matches
-    Entails(Literal), // Literal is unassigned and the no other watch matches
-    Unsatisfied,      // more than one literal is unassigned
-    Missing,
-}
-
 #[derive(Clone, Debug)]
 pub enum ClauseSource {
     Formula,
-    Resolution(Vec<ClauseKey>),
+    /// Resolved from the clauses of the formula with the given original indices, which are only noted if the core is tracked
+    Resolution(Vec<u32>),
 }
 
 // }
@@ -45,7 +36,7 @@ pub enum Watch {
 }
 
 #[derive(Clone, Copy, PartialEq)]
-enum WatchStatus {
+pub enum WatchStatus {
     Witness,
     None,
     Conflict,
@@ -59,222 +50,120 @@ pub enum WatchUpdate {
 
 // }
 
-impl StoredClause {
-    pub fn new_from(
-        key: ClauseKey,
-        clause: ClauseVec,
-        source: ClauseSource,
-        valuation: &impl Valuation,
-        variables: &mut [Variable],
-    ) -> StoredClause {
-        let figured_out = figure_out_intial_watches(clause.clone(), valuation);
-        let stored_clause = StoredClause {
+impl<'store> StoredClause<'store> {
+    pub fn new(key: ClauseKey, lbd: u32, origins: &'store [u32], literals: &'store [u32]) -> Self {
+        StoredClause {
             key,
-            lbd: UnsafeCell::new(0),
-            source,
-            clause,
-            the_wc: figured_out.clone(),
-            cached_watches: (figured_out[0], figured_out[1]),
-        };
-
-        unsafe {
-            let current_a = stored_clause.get_watched(Watch::A);
-            variables
-                .get_unchecked(current_a.v_id())
-                .watch_added(stored_clause.key, current_a.polarity);
-
-            let current_b = stored_clause.get_watched(Watch::B);
-            variables
-                .get_unchecked(current_b.v_id())
-                .watch_added(stored_clause.key, current_b.polarity);
+            lbd,
+            origins,
+            literals,
         }
-
-        stored_clause
     }
 
     pub fn key(&self) -> ClauseKey {
         self.key
     }
 
-    pub fn source(&self) -> &ClauseSource {
-        &self.source
+    /// The original indices of the clauses of the formula a learnt clause was resolved from, if noted.
+    pub fn origins(&self) -> &'store [u32] {
+        self.origins
     }
 
     pub fn literal_at(&self, position: usize) -> Literal {
-        unsafe { *self.clause.get_unchecked(position) }
+        Literal::from_code(self.literals[position])
     }
 
     pub fn get_watched(&self, watch: Watch) -> Literal {
         match watch {
-            Watch::A => self.cached_watches.0,
-            Watch::B => self.cached_watches.1,
-        }
-    }
-
-    pub fn get_watched_split(&self, watch: Watch) -> (VariableId, bool) {
-        match watch {
-            Watch::A => {
-                let literal = self.cached_watches.0;
-                (literal.v_id, literal.polarity)
-            }
-            Watch::B => {
-                let literal = self.cached_watches.1;
-                (literal.v_id, literal.polarity)
-            }
-        }
-    }
-
-    pub fn watch_status(&self, val: &impl Valuation, id: VariableId) -> ClauseStatus {
-        let a_literal = self.get_watched(Watch::A);
-
-        let b_literal = self.get_watched(Watch::B);
-
-        if a_literal.v_id != id && b_literal.v_id != id {
-            return ClauseStatus::Missing;
-        }
-
-        match (val.of_v_id(a_literal.v_id), val.of_v_id(b_literal.v_id)) {
-            (None, None) => ClauseStatus::Unsatisfied,
-            (Some(a), None) if a == a_literal.polarity => ClauseStatus::Satisfied,
-            (None, Some(b)) if b == b_literal.polarity => ClauseStatus::Satisfied,
-            (Some(_), None) => ClauseStatus::Entails(b_literal),
-            (None, Some(_)) => ClauseStatus::Entails(a_literal),
-            (Some(a), Some(b)) if a == a_literal.polarity || b == b_literal.polarity => {
-                ClauseStatus::Satisfied
-            }
-            (Some(_), Some(_)) => ClauseStatus::Conflict,
-        }
-    }
-
-    pub fn set_lbd(&self, vars: &[Variable]) {
-        unsafe {
-            *self.lbd.get() = self.lbd(vars);
+            Watch::A => self.literal_at(0),
+            Watch::B => self.literal_at(1),
         }
     }
 
     pub fn get_set_lbd(&self) -> usize {
-        unsafe { *self.lbd.get() }
+        self.lbd as usize
     }
 
+    /// The literals of the clause, in the order of the stored clause
     pub fn clause_clone(&self) -> ClauseVec {
-        self.clause.clone()
+        self.literals().collect()
     }
 
-    pub fn update_watch(&mut self, watch: Watch, valuation: &impl Valuation) -> WatchUpdate {
-        let mut replacement = WatchUpdate::NoUpdate;
-
-        let mut idx = 2;
-        let length = self.the_wc.len();
-        'search_loop: for _ in 2..length {
-            let the_literal = unsafe { *self.the_wc.get_unchecked(idx) };
-            match get_status(the_literal, valuation) {
-                WatchStatus::None => {
-                    replacement = WatchUpdate::FromTo(self.get_watched(watch), the_literal);
-                    break 'search_loop;
-                }
-                WatchStatus::Witness => {
-                    replacement = WatchUpdate::FromTo(self.get_watched(watch), the_literal);
-                    break 'search_loop;
-                }
-                WatchStatus::Conflict => {
-                    idx += 1;
-                }
-            }
-        }
-
-        match replacement {
-            WatchUpdate::FromTo(_, the_literal) => {
-                let clause_index = match watch {
-                    Watch::A => {
-                        self.cached_watches.0 = the_literal;
-                        0
-                    }
-                    Watch::B => {
-                        self.cached_watches.1 = the_literal;
-                        1
-                    }
-                };
-                let mix_up = idx / 4;
-                if mix_up > 2 {
-                    self.the_wc.swap(mix_up, idx);
-                    self.the_wc.swap(clause_index, mix_up);
-                } else {
-                    self.the_wc.swap(clause_index, idx);
-                }
-            }
-            WatchUpdate::NoUpdate => {}
-        }
-
-        replacement
+    /// The literals of the clause, ordered as a formula clause
+    pub fn sorted_clause(&self) -> ClauseVec {
+        let mut the_clause = self.clause_clone();
+        the_clause.sort_unstable();
+        the_clause
     }
 }
 
-impl std::fmt::Display for StoredClause {
+impl std::fmt::Display for StoredClause<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.clause.as_string())
+        write!(f, "{}", self.sorted_clause().as_string())
     }
 }
 
 /// Lift the method from the clause stored to the stored clause
-impl Clause for StoredClause {
+impl Clause for StoredClause<'_> {
     fn literals(&self) -> impl Iterator<Item = Literal> {
-        self.clause.literals()
+        self.literals.iter().map(|&code| Literal::from_code(code))
     }
 
     fn variables(&self) -> impl Iterator<Item = VariableId> {
-        self.clause.variables()
+        self.literals().map(|literal| literal.v_id())
     }
 
     fn is_sat_on(&self, valuation: &ValuationVec) -> bool {
-        self.clause.is_sat_on(valuation)
+        self.clause_clone().is_sat_on(valuation)
     }
 
     fn is_unsat_on(&self, valuation: &ValuationVec) -> bool {
-        self.clause.is_unsat_on(valuation)
+        self.clause_clone().is_unsat_on(valuation)
     }
 
     fn find_unit_literal<T: Valuation>(&self, valuation: &T) -> Option<Literal> {
-        self.clause.find_unit_literal(valuation)
+        self.clause_clone().find_unit_literal(valuation)
     }
 
     fn collect_choices<T: Valuation>(&self, valuation: &T) -> Option<Vec<Literal>> {
-        self.clause.collect_choices(valuation)
+        self.clause_clone().collect_choices(valuation)
     }
 
     fn as_string(&self) -> String {
-        self.clause.as_string()
+        self.sorted_clause().as_string()
     }
 
     fn as_dimacs(&self, variables: &[Variable]) -> String {
-        self.clause.as_dimacs(variables)
+        self.sorted_clause().as_dimacs(variables)
     }
 
     fn is_empty(&self) -> bool {
-        self.clause.is_empty()
+        self.literals.is_empty()
     }
 
     fn to_vec(self) -> ClauseVec {
-        self.clause
+        self.sorted_clause()
     }
 
     fn length(&self) -> usize {
-        self.clause.len()
+        self.literals.len()
     }
 
     fn asserts(&self, val: &impl Valuation) -> Option<Literal> {
-        self.clause.asserts(val)
+        self.clause_clone().asserts(val)
     }
 
-    fn lbd(&self, variables: &[Variable]) -> usize {
-        self.clause.lbd(variables)
+    fn lbd(&self, decision_levels: &[Option<LevelIndex>]) -> usize {
+        self.clause_clone().lbd(decision_levels)
     }
 
     fn find_literal_by_id(&self, id: VariableId) -> Option<Literal> {
-        self.clause.find_literal_by_id(id)
+        self.literals().find(|literal| literal.v_id() == id)
     }
 }
 
-fn figure_out_intial_watches(clause: ClauseVec, val: &impl Valuation) -> Vec<Literal> {
+/// Reorders the clause so the first two literals are suitable watches on the valuation
+pub fn figure_out_intial_watches(clause: ClauseVec, val: &impl Valuation) -> Vec<Literal> {
     let length = clause.len();
     let mut the_wc = clause;
     let mut watch_a = 0;
@@ -320,21 +209,51 @@ fn figure_out_intial_watches(clause: ClauseVec, val: &impl Valuation) -> Vec<Lit
                     if a_status == WatchStatus::Conflict {
                         watch_a = index;
                         a_status = WatchStatus::Witness;
+                    } else if b_status == WatchStatus::Conflict {
+                        watch_b = index;
+                        b_status = WatchStatus::Witness;
                     }
                 }
             }
         }
-        the_wc.swap(0, watch_a);
-        the_wc.swap(1, watch_b);
     }
 
+    // the watches are indexed on the clause as given, so the second swap accounts for the first
+    the_wc.swap(0, watch_a);
+    if watch_b == 0 {
+        watch_b = watch_a;
+    }
+    the_wc.swap(1, watch_b);
+
     the_wc
 }
 
-fn get_status(literal: Literal, valuation: &impl Valuation) -> WatchStatus {
-    match valuation.of_v_id(literal.v_id) {
+pub fn get_status(literal: Literal, valuation: &impl Valuation) -> WatchStatus {
+    match valuation.of_literal(literal) {
         None => WatchStatus::None,
-        Some(polarity) if polarity == literal.polarity => WatchStatus::Witness,
-        Some(_) => WatchStatus::Conflict,
+        Some(true) => WatchStatus::Witness,
+        Some(false) => WatchStatus::Conflict,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_watches_avoid_false_literals() {
+        let mut valuation = ValuationVec::new_for_variables(4);
+        let _ = valuation.update_value(Literal::new(0, false));
+        let _ = valuation.update_value(Literal::new(1, false));
+
+        let clause = vec![
+            Literal::new(0, true),
+            Literal::new(1, true),
+            Literal::new(2, true),
+            Literal::new(3, true),
+        ];
+        let the_wc = figure_out_intial_watches(clause, &valuation);
+        assert_eq!(valuation.of_literal(the_wc[0]), None);
+        assert_eq!(valuation.of_literal(the_wc[1]), None);
     }
 }
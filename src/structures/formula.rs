@@ -1,12 +1,32 @@
-use crate::structures::{
-    clause::{clause_vec::ClauseVec, Clause},
-    literal::Literal,
-    variable::Variable,
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    errors::ParseError,
+    structures::{
+        clause::{clause_vec::ClauseVec, Clause},
+        literal::Literal,
+        variable::{Variable, VariableId},
+    },
 };
 
+#[derive(Clone, Default)]
 pub struct Formula {
     pub variables: Vec<Variable>,
     pub clauses: Vec<ClauseVec>,
+    /// The variables to project onto when counting, if given
+    pub projection: Option<Vec<VariableId>>,
+    /// The count of variables stated in the preface, if any
+    pub preface_variable_count: Option<usize>,
+    /// Weighted soft clauses, if the formula was read from WCNF or has an objective
+    pub soft_clauses: Vec<(u64, ClauseVec)>,
+    /// A constant added to the weight of the soft clauses which are not satisfied to give the cost of a model
+    pub cost_offset: i64,
+    /// Cardinality constraints, each requiring at least the given count of the literals to be true
+    pub cardinality_constraints: Vec<(usize, ClauseVec)>,
+    /// XOR constraints, each requiring the count of true variables to be odd if the parity is true and even otherwise
+    pub xor_clauses: Vec<(Vec<VariableId>, bool)>,
+    /// Cubes, each a conjunction of literals, such as from a split of the formula or the assumptions of a query, with the count of clauses read before the cube
+    pub cubes: Vec<(usize, ClauseVec)>,
 }
 
 impl Formula {
@@ -14,6 +34,13 @@ impl Formula {
         Formula {
             variables: vec![],
             clauses: vec![],
+            projection: None,
+            preface_variable_count: None,
+            soft_clauses: vec![],
+            cost_offset: 0,
+            cardinality_constraints: vec![],
+            xor_clauses: vec![],
+            cubes: vec![],
         }
     }
 
@@ -25,21 +52,138 @@ impl Formula {
         self.variables.len()
     }
 
-    pub fn add_clause(&mut self, string: &str) {
-        let clause = self.clause_vec_from_string(string);
+    /// Adds a variable to the formula, which occurs in no clause
+    pub fn fresh_variable(&mut self, name: &str) -> VariableId {
+        let v_id = self.variables.len() as VariableId;
+        self.variables.push(Variable::new(name, v_id));
+        v_id
+    }
+
+    /// Adds each variable stated in the preface which has not been seen, so the variables of the formula are those of the preface.
+    pub fn add_preface_variables(&mut self) {
+        let stated = self.preface_variable_count.unwrap_or(0);
+        let seen = self
+            .variables
+            .iter()
+            .map(|variable| variable.name().to_string())
+            .collect::<HashSet<_>>();
+        for name in (1..=stated).map(|index| index.to_string()) {
+            if !seen.contains(&name) {
+                self.fresh_variable(&name);
+            }
+        }
+    }
+
+    pub fn add_clause(&mut self, string: &str) -> Result<(), ParseError> {
+        let clause = self.clause_vec_from_string(string)?;
         self.clauses.push(clause);
+        Ok(())
+    }
+
+    pub fn add_soft_clause(&mut self, weight: u64, string: &str) -> Result<(), ParseError> {
+        let clause = self.clause_vec_from_string(string)?;
+        self.soft_clauses.push((weight, clause));
+        Ok(())
+    }
+
+    /// Adds the constraint that at least `degree` of the literals of the string are true
+    pub fn add_cardinality_constraint(
+        &mut self,
+        degree: usize,
+        string: &str,
+    ) -> Result<(), ParseError> {
+        let literals = self.clause_vec_from_string(string)?;
+        self.cardinality_constraints.push((degree, literals));
+        Ok(())
+    }
+
+    /// Adds the constraint that an odd count of the literals of the string are true.
+    /// As the negation of a literal flips the parity, the constraint is kept on the variables of the literals.
+    pub fn add_xor_clause(&mut self, string: &str) -> Result<(), ParseError> {
+        let mut parity = true;
+        let mut variables = vec![];
+        for name in string.split_whitespace() {
+            let the_literal = Literal::from_string(name, &mut self.variables)?;
+            if !the_literal.polarity() {
+                parity = !parity;
+            }
+            variables.push(the_literal.v_id());
+        }
+        self.xor_clauses.push((variables, parity));
+        Ok(())
+    }
+
+    /// Adds an XOR constraint for each set of clauses which together are the direct encoding of an XOR over at most `max_size` variables, returning the count of XORs recovered.
+    ///
+    /// The direct encoding of an XOR over k variables is the 2^(k-1) clauses on the variables which each exclude one assignment of the wrong parity.
+    /// A clause excludes the assignment which falsifies each literal, and the parity of this assignment is the parity of the count of negative literals.
+    /// The clauses are kept, and as the negative literals of a clause are noted by a 32 bit pattern, `max_size` is at most 32.
+    pub fn recover_xors(&mut self, max_size: usize) -> usize {
+        let max_size = max_size.min(u32::BITS as usize);
+        // for each set of variables, the patterns of negative literals of clauses on the variables, split by parity
+        let mut patterns: HashMap<Vec<VariableId>, [Vec<u32>; 2]> = HashMap::new();
+        for clause in &self.clauses {
+            if clause.len() < 3 || clause.len() > max_size {
+                continue;
+            }
+            let variables = clause.variables().collect::<Vec<_>>();
+            if variables.windows(2).any(|pair| pair[0] == pair[1]) {
+                continue;
+            }
+            let negatives = clause
+                .iter()
+                .enumerate()
+                .filter(|(_, literal)| !literal.polarity())
+                .fold(0_u32, |pattern, (index, _)| pattern | (1 << index));
+            patterns.entry(variables).or_default()[negatives.count_ones() as usize % 2]
+                .push(negatives);
+        }
+
+        let mut recovered = 0;
+        for (variables, mut by_parity) in patterns {
+            for (excluded_parity, found) in by_parity.iter_mut().enumerate() {
+                found.sort_unstable();
+                found.dedup();
+                if found.len() == 1 << (variables.len() - 1) {
+                    self.xor_clauses
+                        .push((variables.clone(), excluded_parity == 0));
+                    recovered += 1;
+                }
+            }
+        }
+        recovered
+    }
+
+    /// Adds the conjunction of the literals of the string as a cube
+    pub fn add_cube(&mut self, string: &str) -> Result<(), ParseError> {
+        let the_cube = self.clause_vec_from_string(string)?;
+        self.cubes.push((self.clauses.len(), the_cube));
+        Ok(())
     }
 
-    fn clause_vec_from_string(&mut self, string: &str) -> ClauseVec {
+    /// Adds the variables named in the string to the projection, noting any variable which has not been seen
+    pub fn add_projection(&mut self, string: &str) -> Result<(), ParseError> {
+        let mut projection = self.projection.take().unwrap_or_default();
+        for name in string.split_whitespace() {
+            let the_literal = Literal::from_string(name, &mut self.variables)?;
+            if !projection.contains(&the_literal.v_id()) {
+                projection.push(the_literal.v_id());
+            }
+        }
+        self.projection = Some(projection);
+        Ok(())
+    }
+
+    fn clause_vec_from_string(&mut self, string: &str) -> Result<ClauseVec, ParseError> {
         let string_lterals = string.split_whitespace();
         let mut the_clause = vec![];
         for string_literal in string_lterals {
-            let the_literal = Literal::from_string(string_literal, &mut self.variables);
+            let the_literal = Literal::from_string(string_literal, &mut self.variables)?;
             the_clause.push(the_literal)
         }
         the_clause.sort_unstable();
         the_clause.dedup();
-        the_clause
+        Ok(the_clause)
     }
 }
 
@@ -59,6 +203,49 @@ impl std::fmt::Display for Formula {
         for clause in &self.clauses {
             writeln!(f, "|   {}", clause.as_string())?;
         }
+        for (degree, literals) in &self.cardinality_constraints {
+            writeln!(f, "|   {} >= {degree}", literals.as_string())?;
+        }
+        for (variables, parity) in &self.xor_clauses {
+            let sum = variables
+                .iter()
+                .map(|v_id| v_id.to_string())
+                .collect::<Vec<_>>()
+                .join(" + ");
+            writeln!(f, "|   {sum} = {}", *parity as u8)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovered_xors() {
+        // the direct encoding of 1 + 2 + 3 = 1
+        let mut formula =
+            Formula::from_dimacs("1 2 3 0\n1 -2 -3 0\n-1 2 -3 0\n-1 -2 3 0\n").unwrap();
+        assert_eq!(formula.recover_xors(3), 1);
+        assert_eq!(formula.xor_clauses, vec![(vec![0, 1, 2], true)]);
+
+        // a clause longer than the pattern of negative literals is passed over
+        let long_clause = (1..=40).map(|v| format!("-{v} ")).collect::<String>();
+        let mut formula = Formula::from_dimacs(&format!("{long_clause}0\n")).unwrap();
+        assert_eq!(formula.recover_xors(64), 0);
+    }
+
+    #[test]
+    fn preface_variables() {
+        let mut formula = Formula::from_dimacs("p cnf 5 2\nc p show 4 0\n3 -1 0\n1 0\n").unwrap();
+        assert_eq!(formula.variable_count(), 3);
+        formula.add_preface_variables();
+        let names = formula
+            .variables
+            .iter()
+            .map(|v| v.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["4", "3", "1", "2", "5"]);
+    }
+}
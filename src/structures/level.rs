@@ -1,8 +1,11 @@
 use std::fmt::Debug;
 
-use crate::structures::{
-    literal::{Literal, LiteralSource},
-    solve::Solve,
+use crate::{
+    errors::SolverError,
+    structures::{
+        literal::{Literal, LiteralSource},
+        solve::Solve,
+    },
 };
 
 pub type LevelIndex = usize;
@@ -10,14 +13,17 @@ pub type LevelIndex = usize;
 #[derive(Debug)]
 pub struct Level {
     index: LevelIndex,
+    /// The length of the trail when the level was added, and so the position of the choice of the level, if any
+    trail_position: usize,
     pub choice: Option<Literal>,
     pub observations: Vec<(LiteralSource, Literal)>,
 }
 
 impl Level {
-    pub fn new(index: LevelIndex) -> Self {
+    pub fn new(index: LevelIndex, trail_position: usize) -> Self {
         Level {
             index,
+            trail_position,
             choice: None,
             observations: vec![],
         }
@@ -27,12 +33,20 @@ impl Level {
         self.index
     }
 
-    pub fn record_literal(&mut self, literal: Literal, source: &LiteralSource) {
+    pub fn trail_position(&self) -> usize {
+        self.trail_position
+    }
+
+    pub fn record_literal(
+        &mut self,
+        literal: Literal,
+        source: &LiteralSource,
+    ) -> Result<(), SolverError> {
         match source {
             LiteralSource::Choice => {
-                self.choice
-                    .is_some()
-                    .then(|| panic!("Attempting to make multiple choices on a single level"));
+                if self.choice.is_some() {
+                    return Err(SolverError::MultipleChoices { level: self.index });
+                }
 
                 self.choice = Some(literal);
             }
@@ -41,6 +55,7 @@ impl Level {
             | LiteralSource::Resolution(_)
             | LiteralSource::StoredClause(_) => self.observations.push((source.clone(), literal)),
         }
+        Ok(())
     }
 
     pub fn observations(&self) -> &[(LiteralSource, Literal)] {
@@ -60,7 +75,7 @@ impl Level {
 impl Solve {
     pub fn add_fresh_level(&mut self) -> LevelIndex {
         let index = self.levels.len();
-        let the_level = Level::new(index);
+        let the_level = Level::new(index, self.trail.len());
         self.levels.push(the_level);
         index
     }
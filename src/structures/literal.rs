@@ -1,44 +1,58 @@
-use crate::structures::{
-    solve::ClauseKey,
-    variable::{Variable, VariableId},
+use crate::{
+    errors::ParseError,
+    structures::{
+        solve::ClauseKey,
+        variable::{Variable, VariableId},
+    },
 };
 
-#[derive(Clone, Copy, Debug)]
-pub struct Literal {
-    pub v_id: VariableId,
-    pub polarity: bool,
-}
+/// A literal, packed as twice the id of the variable plus one if the literal is positive.
+///
+/// So, the negation of a literal differs only on the lowest bit, and per-literal arrays may be indexed directly by the literal.
+/// Literals are ordered by id and polarity on a tie with false < true.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Literal(u32);
 
 /// how a literal was settled
 #[derive(Clone, Debug)]
 pub enum LiteralSource {
-    Choice,                     // a choice made where the alternative may make a SAT difference
+    Choice,                  // a choice made where the alternative may make a SAT difference
     HobsonChoice, // a choice made with a guarantee that the alternative would make no SAT difference
     StoredClause(ClauseKey), // the literal must be the case for SAT given some valuation
-    Resolution(Vec<ClauseKey>), // there was no reason to store the resolved clause
+    Resolution(Vec<u32>), // there was no reason to store the resolved clause, which was resolved from the original clauses
     Assumption,
 }
 
 impl Literal {
     pub fn negate(&self) -> Self {
-        Literal {
-            v_id: self.v_id,
-            polarity: !self.polarity,
-        }
+        Literal(self.0 ^ 1)
     }
 
     pub fn new(variable: VariableId, polarity: bool) -> Self {
-        Literal {
-            v_id: variable,
-            polarity,
-        }
+        Literal((variable << 1) | polarity as u32)
+    }
+
+    /// The literal from the packed representation
+    pub fn from_code(code: u32) -> Self {
+        Literal(code)
     }
 
-    pub fn from_string(string: &str, vars: &mut Vec<Variable>) -> Literal {
+    /// The packed representation of the literal
+    pub fn code(&self) -> u32 {
+        self.0
+    }
+
+    /// The index of the literal in any per-literal array
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn from_string(string: &str, vars: &mut Vec<Variable>) -> Result<Literal, ParseError> {
         let trimmed_string = string.trim();
 
         if trimmed_string.is_empty() || trimmed_string == "-" {
-            panic!("No variable when creating literal from string");
+            return Err(ParseError::NoVariable);
         }
 
         let polarity = !trimmed_string.starts_with('-');
@@ -58,50 +72,41 @@ impl Literal {
                 the_id
             }
         };
-        Literal::new(the_variable, polarity)
+        Ok(Literal::new(the_variable, polarity))
     }
 
-    pub fn v_id(&self) -> usize {
-        self.v_id as usize
+    pub fn v_id(&self) -> VariableId {
+        self.0 >> 1
     }
-}
 
-impl PartialOrd for Literal {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    pub fn polarity(&self) -> bool {
+        self.0 & 1 == 1
     }
 }
 
-/// Literals are ordered by id and polarity on a tie with false < true.
-impl Ord for Literal {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.v_id == other.v_id {
-            if self.polarity == other.polarity {
-                std::cmp::Ordering::Equal
-            } else if self.polarity {
-                std::cmp::Ordering::Greater
-            } else {
-                std::cmp::Ordering::Less
-            }
-        } else {
-            self.v_id.cmp(&other.v_id)
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.polarity() {
+            true => write!(f, "{}", self.v_id()),
+            false => write!(f, "-{}", self.v_id()),
         }
     }
 }
 
-impl PartialEq for Literal {
-    fn eq(&self, other: &Self) -> bool {
-        self.v_id == other.v_id && self.polarity == other.polarity
-    }
-}
-
-impl Eq for Literal {}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl std::fmt::Display for Literal {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self.polarity {
-            true => write!(f, "{}", self.v_id),
-            false => write!(f, "-{}", self.v_id),
-        }
+    #[test]
+    fn packed_literal_check() {
+        let literal = Literal::new(3, false);
+        assert_eq!(literal.v_id(), 3);
+        assert!(!literal.polarity());
+        assert_eq!(literal.negate(), Literal::new(3, true));
+        assert_eq!(literal.negate().negate(), literal);
+        assert_eq!(literal.index() ^ 1, literal.negate().index());
+        assert_eq!(Literal::from_code(literal.code()), literal);
+        assert!(Literal::new(3, false) < Literal::new(3, true));
+        assert!(Literal::new(3, true) < Literal::new(4, false));
     }
 }
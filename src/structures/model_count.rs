@@ -0,0 +1,151 @@
+/// A count of models, of arbitrary size.
+///
+/// The count is kept as little-endian 32-bit limbs, without any trailing zero limb, and so zero has no limbs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModelCount {
+    limbs: Vec<u32>,
+}
+
+impl ModelCount {
+    pub fn zero() -> Self {
+        ModelCount { limbs: vec![] }
+    }
+
+    pub fn one() -> Self {
+        ModelCount { limbs: vec![1] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn add(&self, other: &ModelCount) -> ModelCount {
+        let length = std::cmp::max(self.limbs.len(), other.limbs.len());
+        let mut limbs = Vec::with_capacity(length + 1);
+        let mut carry = 0_u64;
+        for i in 0..length {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64
+                + *other.limbs.get(i).unwrap_or(&0) as u64
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        ModelCount { limbs }
+    }
+
+    pub fn mul(&self, other: &ModelCount) -> ModelCount {
+        if self.is_zero() || other.is_zero() {
+            return ModelCount::zero();
+        }
+        let mut limbs = vec![0_u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0_u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        let mut the_count = ModelCount { limbs };
+        the_count.trim();
+        the_count
+    }
+
+    /// Multiplies the count by two to the power of the exponent
+    pub fn double(&mut self, exponent: usize) {
+        if self.is_zero() || exponent == 0 {
+            return;
+        }
+        let (whole_limbs, bits) = (exponent / 32, exponent % 32);
+        if bits > 0 {
+            let mut carry = 0;
+            for limb in &mut self.limbs {
+                let shifted = ((*limb as u64) << bits) | carry;
+                *limb = shifted as u32;
+                carry = shifted >> 32;
+            }
+            if carry > 0 {
+                self.limbs.push(carry as u32);
+            }
+        }
+        self.limbs.splice(0..0, std::iter::repeat_n(0, whole_limbs));
+    }
+
+    /// An estimate of the base 10 logarithm of the count
+    pub fn log10(&self) -> f64 {
+        match self.limbs.len() {
+            0 => f64::NEG_INFINITY,
+            1 => (self.limbs[0] as f64).log10(),
+            n => {
+                let top = ((self.limbs[n - 1] as u64) << 32 | self.limbs[n - 2] as u64) as f64;
+                top.log10() + (32 * (n - 2)) as f64 * std::f64::consts::LOG10_2
+            }
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl std::fmt::Display for ModelCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // repeated division by a power of ten, each remainder giving nine digits
+        const CHUNK: u64 = 1_000_000_000;
+        let mut limbs = self.limbs.clone();
+        let mut chunks = vec![];
+        while !limbs.is_empty() {
+            let mut remainder = 0_u64;
+            for limb in limbs.iter_mut().rev() {
+                let current = (remainder << 32) | *limb as u64;
+                *limb = (current / CHUNK) as u32;
+                remainder = current % CHUNK;
+            }
+            chunks.push(remainder);
+            while limbs.last() == Some(&0) {
+                limbs.pop();
+            }
+        }
+
+        write!(f, "{}", chunks.pop().unwrap_or(0))?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_count_arithmetic() {
+        let mut count = ModelCount::one();
+        count.double(100);
+        assert_eq!(count.to_string(), "1267650600228229401496703205376");
+
+        let sum = count.add(&ModelCount::one());
+        assert_eq!(sum.to_string(), "1267650600228229401496703205377");
+
+        let product = sum.mul(&sum);
+        assert_eq!(
+            product.to_string(),
+            "1606938044258990275541962092343697903722659452585786241712129"
+        );
+        assert!((product.log10() - 60.206).abs() < 0.001);
+
+        assert!(product.mul(&ModelCount::zero()).is_zero());
+        assert_eq!(ModelCount::zero().to_string(), "0");
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::structures::{formula::Formula, literal::Literal, variable::VariableId};
+
+/// How a linear pseudo-Boolean constraint is encoded to clauses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PbEncoding {
+    /// A sequential weight counter, of size O(n K) for n terms and a bound of K
+    SequentialCounter,
+    /// A generalised totalizer, with a variable for each sum a node of a binary tree may take
+    Totalizer,
+    /// A BDD over the terms by decreasing coefficient, with nodes shared across intervals of the degree
+    Bdd,
+}
+
+/// The relation between the sum of the terms and the degree of a constraint, as read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    AtLeast,
+    AtMost,
+    Equal,
+}
+
+/// A linear pseudo-Boolean constraint, requiring the sum of the coefficients of the true literals to be at least the degree.
+///
+/// Coefficients are positive, and no variable occurs in two terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinearConstraint {
+    pub terms: Vec<(u64, Literal)>,
+    pub degree: u64,
+}
+
+impl LinearConstraint {
+    /// The constraints in normal form which together are equivalent to the terms in the given relation to the degree.
+    pub fn from_terms(
+        terms: &[(i64, Literal)],
+        relation: Relation,
+        degree: i64,
+    ) -> Vec<LinearConstraint> {
+        let negated = || {
+            terms
+                .iter()
+                .map(|(coefficient, literal)| (-(*coefficient as i128), *literal))
+                .collect::<Vec<_>>()
+        };
+        let terms = terms
+            .iter()
+            .map(|(coefficient, literal)| (*coefficient as i128, *literal))
+            .collect::<Vec<_>>();
+        let degree = degree as i128;
+
+        match relation {
+            Relation::AtLeast => vec![Self::normalise(terms, degree)],
+            Relation::AtMost => vec![Self::normalise(negated(), -degree)],
+            Relation::Equal => vec![
+                Self::normalise(terms, degree),
+                Self::normalise(negated(), -degree),
+            ],
+        }
+    }
+
+    /// The constraint in normal form equivalent to the sum of the terms being at least the degree.
+    ///
+    /// A term with a negative coefficient is rewritten on the negation of the literal, as a l = a + (-a) -l.
+    /// Likewise, terms on a literal and its negation are merged, as a l + b -l = b + (a - b) l.
+    fn normalise(terms: Vec<(i128, Literal)>, mut degree: i128) -> LinearConstraint {
+        let mut coefficients: HashMap<VariableId, i128> = HashMap::new();
+        let mut order = vec![];
+        for (coefficient, literal) in terms {
+            // each term is kept as a coefficient on the positive literal
+            let positive = match literal.polarity() {
+                true => coefficient,
+                false => {
+                    degree -= coefficient;
+                    -coefficient
+                }
+            };
+            let entry = coefficients.entry(literal.v_id()).or_insert_with(|| {
+                order.push(literal.v_id());
+                0
+            });
+            *entry += positive;
+        }
+
+        let mut normal_terms = vec![];
+        for v_id in order {
+            let coefficient = coefficients[&v_id];
+            if coefficient > 0 {
+                normal_terms.push((coefficient, Literal::new(v_id, true)));
+            } else if coefficient < 0 {
+                degree -= coefficient;
+                normal_terms.push((-coefficient, Literal::new(v_id, false)));
+            }
+        }
+
+        let degree = degree.clamp(0, u64::MAX as i128) as u64;
+        LinearConstraint {
+            terms: normal_terms
+                .into_iter()
+                .map(|(coefficient, literal)| (coefficient.min(u64::MAX as i128) as u64, literal))
+                .collect(),
+            degree,
+        }
+    }
+}
+
+impl Formula {
+    fn auxiliary_literal(&mut self) -> Literal {
+        let name = format!("_p{}", self.variables.len());
+        Literal::new(self.fresh_variable(&name), true)
+    }
+
+    /// Adds clauses to the formula which hold only if the constraint holds, using the given encoding.
+    ///
+    /// Each coefficient greater than the degree is reduced to the degree, and clauses are only for one direction of each auxiliary variable.
+    pub fn add_linear_constraint(&mut self, constraint: LinearConstraint, encoding: PbEncoding) {
+        let degree = constraint.degree;
+        if degree == 0 {
+            return;
+        }
+        let mut terms = constraint
+            .terms
+            .into_iter()
+            .map(|(coefficient, literal)| (coefficient.min(degree), literal))
+            .collect::<Vec<_>>();
+        let total = terms.iter().fold(0_u64, |sum, (coefficient, _)| {
+            sum.saturating_add(*coefficient)
+        });
+
+        if total < degree {
+            self.clauses.push(vec![]);
+            return;
+        }
+        // any literal suffices by itself, so the constraint is a clause
+        if terms.iter().all(|(coefficient, _)| *coefficient == degree) {
+            self.clauses
+                .push(terms.into_iter().map(|(_, literal)| literal).collect());
+            return;
+        }
+
+        match encoding {
+            PbEncoding::SequentialCounter => {
+                let negated = terms
+                    .iter()
+                    .map(|(coefficient, literal)| (*coefficient, literal.negate()))
+                    .collect::<Vec<_>>();
+                self.encode_sequential_counter(&negated, total - degree);
+            }
+            PbEncoding::Totalizer => {
+                let negated = terms
+                    .iter()
+                    .map(|(coefficient, literal)| (*coefficient, literal.negate()))
+                    .collect::<Vec<_>>();
+                self.encode_totalizer(&negated, total - degree);
+            }
+            PbEncoding::Bdd => {
+                terms.sort_by_key(|(coefficient, _)| std::cmp::Reverse(*coefficient));
+                self.encode_bdd(&terms, degree);
+            }
+        }
+    }
+
+    /// Encodes the sum of the coefficients of the true literals being at most the bound, with a register of the sum so far after each term
+    fn encode_sequential_counter(&mut self, terms: &[(u64, Literal)], bound: u64) {
+        let bound = bound as usize;
+        // the register of the previous term, with the variable at index j - 1 true if the sum so far is at least j
+        let mut previous: Vec<Literal> = vec![];
+
+        for (index, &(coefficient, literal)) in terms.iter().enumerate() {
+            let weight = coefficient as usize;
+            if weight > bound {
+                self.clauses.push(vec![literal.negate()]);
+                continue;
+            }
+            // the literal may not be true if the sum so far is greater than the bound less the weight
+            if let Some(&overflow) = previous.get(bound - weight) {
+                self.clauses.push(vec![literal.negate(), overflow.negate()]);
+            }
+            if index + 1 == terms.len() {
+                break;
+            }
+
+            let current = (0..bound)
+                .map(|_| self.auxiliary_literal())
+                .collect::<Vec<_>>();
+            for &sum in &current[..weight] {
+                self.clauses.push(vec![literal.negate(), sum]);
+            }
+            for (j, &before) in previous.iter().enumerate() {
+                self.clauses.push(vec![before.negate(), current[j]]);
+                if j + weight < bound {
+                    self.clauses
+                        .push(vec![literal.negate(), before.negate(), current[j + weight]]);
+                }
+            }
+            previous = current;
+        }
+    }
+
+    /// Encodes the sum of the coefficients of the true literals being at most the bound
+    fn encode_totalizer(&mut self, terms: &[(u64, Literal)], bound: u64) {
+        let root = self.totalizer_node(terms, bound.saturating_add(1));
+        if let Some((_, overflow)) = root.iter().find(|(sum, _)| *sum > bound) {
+            self.clauses.push(vec![overflow.negate()]);
+        }
+    }
+
+    /// The sums the terms may take, each capped at the limit, and for each sum a literal which is true if the terms sum to at least the sum
+    fn totalizer_node(&mut self, terms: &[(u64, Literal)], limit: u64) -> Vec<(u64, Literal)> {
+        if let [(coefficient, literal)] = terms {
+            return vec![(std::cmp::min(*coefficient, limit), *literal)];
+        }
+
+        let (left, right) = terms.split_at(terms.len() / 2);
+        let left = self.totalizer_node(left, limit);
+        let right = self.totalizer_node(right, limit);
+
+        // each sum of the node is given a literal when first seen, and the sums of a pair of terms are capped at the limit
+        let mut implications = left
+            .iter()
+            .chain(&right)
+            .map(|(sum, literal)| (vec![literal.negate()], *sum))
+            .collect::<Vec<_>>();
+        for (a, left_literal) in &left {
+            for (b, right_literal) in &right {
+                implications.push((
+                    vec![left_literal.negate(), right_literal.negate()],
+                    a.saturating_add(*b).min(limit),
+                ));
+            }
+        }
+
+        let mut node = BTreeMap::new();
+        for (mut clause, sum) in implications {
+            let output = *node.entry(sum).or_insert_with(|| self.auxiliary_literal());
+            clause.push(output);
+            self.clauses.push(clause);
+        }
+        node.into_iter().collect()
+    }
+
+    /// Encodes the sum of the coefficients of the true literals being at least the degree, with terms ordered by decreasing coefficient
+    fn encode_bdd(&mut self, terms: &[(u64, Literal)], degree: u64) {
+        // the sum of the coefficients of the terms from each index onwards
+        let mut remaining = vec![0_u64; terms.len() + 1];
+        for index in (0..terms.len()).rev() {
+            remaining[index] = remaining[index + 1].saturating_add(terms[index].0);
+        }
+
+        let mut nodes = vec![vec![]; terms.len()];
+        let (root, _) = self.bdd_node(terms, &remaining, &mut nodes, 0, degree as i128);
+        match root {
+            BddNode::True => {}
+            BddNode::False => self.clauses.push(vec![]),
+            BddNode::Literal(literal) => self.clauses.push(vec![literal]),
+        }
+    }
+
+    /// The node for the terms from the index onwards meeting the degree, with the interval of degrees for which the node is the same
+    fn bdd_node(
+        &mut self,
+        terms: &[(u64, Literal)],
+        remaining: &[u64],
+        nodes: &mut [Vec<(i128, i128, BddNode)>],
+        index: usize,
+        degree: i128,
+    ) -> (BddNode, (i128, i128)) {
+        if degree <= 0 {
+            return (BddNode::True, (i128::MIN, 0));
+        }
+        if degree > remaining[index] as i128 {
+            return (BddNode::False, (remaining[index] as i128 + 1, i128::MAX));
+        }
+        if let Some((low, high, node)) = nodes[index]
+            .iter()
+            .find(|(low, high, _)| *low <= degree && degree <= *high)
+        {
+            return (*node, (*low, *high));
+        }
+
+        let (coefficient, literal) = terms[index];
+        let coefficient = coefficient as i128;
+        let (high_node, (high_low, high_high)) =
+            self.bdd_node(terms, remaining, nodes, index + 1, degree - coefficient);
+        let (low_node, (low_low, low_high)) =
+            self.bdd_node(terms, remaining, nodes, index + 1, degree);
+
+        let interval = (
+            std::cmp::max(high_low.saturating_add(coefficient), low_low),
+            std::cmp::min(high_high.saturating_add(coefficient), low_high),
+        );
+
+        let node = if high_node == low_node {
+            high_node
+        } else {
+            let node_literal = self.auxiliary_literal();
+            // the node requires the weaker node of the literal, and the stronger node of the negation unless the literal is true
+            match high_node {
+                BddNode::True => {}
+                BddNode::False => self.clauses.push(vec![node_literal.negate()]),
+                BddNode::Literal(high) => self.clauses.push(vec![node_literal.negate(), high]),
+            }
+            match low_node {
+                BddNode::True => {}
+                BddNode::False => self.clauses.push(vec![node_literal.negate(), literal]),
+                BddNode::Literal(low) => {
+                    self.clauses.push(vec![node_literal.negate(), literal, low])
+                }
+            }
+            BddNode::Literal(node_literal)
+        };
+
+        nodes[index].push((interval.0, interval.1, node));
+        (node, interval)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BddNode {
+    True,
+    False,
+    Literal(Literal),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::solve::Solve;
+
+    #[test]
+    fn normalise_constraints() {
+        let x = Literal::new(0, true);
+        let y = Literal::new(1, true);
+
+        // 2 x - 3 y >= -1 is 2 x + 3 -y >= 2
+        let constraints = LinearConstraint::from_terms(&[(2, x), (-3, y)], Relation::AtLeast, -1);
+        assert_eq!(
+            constraints,
+            vec![LinearConstraint {
+                terms: vec![(2, x), (3, y.negate())],
+                degree: 2
+            }]
+        );
+
+        // x + -x + y = 1 is both y >= 0 and -y >= 1
+        let constraints =
+            LinearConstraint::from_terms(&[(1, x), (1, x.negate()), (1, y)], Relation::Equal, 1);
+        assert_eq!(constraints[0].degree, 0);
+        assert_eq!(constraints[1].terms, vec![(1, y.negate())]);
+        assert_eq!(constraints[1].degree, 1);
+    }
+
+    #[test]
+    fn encodings_agree() {
+        // 3 x1 + 2 x2 + x3 + x4 >= 4 has eight models
+        for encoding in [
+            PbEncoding::SequentialCounter,
+            PbEncoding::Totalizer,
+            PbEncoding::Bdd,
+        ] {
+            let mut formula = Formula::new();
+            let literals = (1..=4)
+                .map(|v| Literal::new(formula.fresh_variable(&v.to_string()), true))
+                .collect::<Vec<_>>();
+            let terms = [3, 2, 1, 1].into_iter().zip(literals).collect::<Vec<_>>();
+            for constraint in LinearConstraint::from_terms(&terms, Relation::AtLeast, 4) {
+                formula.add_linear_constraint(constraint, encoding);
+            }
+
+            let mut the_solve = Solve::from_formula(formula).unwrap();
+            let count = the_solve.count_models(Some(vec![0, 1, 2, 3])).unwrap();
+            assert_eq!(count.to_string(), "8");
+        }
+    }
+}
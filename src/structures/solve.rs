@@ -1,36 +1,101 @@
 mod analysis;
+pub mod backbone;
+pub mod callbacks;
 pub mod clause_store;
 pub mod config;
 pub mod core;
+pub mod count;
+pub mod cube;
+pub mod enumerate;
+pub mod gauss;
+pub mod local_search;
+pub mod maxsat;
+pub mod mus;
+pub mod portfolio;
 mod stats;
+#[cfg(test)]
+mod test_formulas;
 mod the_solve;
+pub mod unsat_core;
 
-use crate::structures::{level::Level, literal::{Literal, LiteralSource}, variable::Variable};
+use crate::structures::{
+    clause::clause_vec::ClauseVec,
+    level::{Level, LevelIndex},
+    literal::Literal,
+    valuation::ValuationVec,
+    variable::{ActivityRep, Variable, VariableId},
+};
 
-use crate::structures::clause::stored_clause::StoredClause;
-use slotmap::{DefaultKey, SlotMap};
+use std::sync::{atomic::AtomicBool, Arc};
 
-use std::collections::VecDeque;
-
-type ClauseStore = SlotMap<DefaultKey, StoredClause>;
+use callbacks::{LearnHook, StopState, TerminateCallback};
+use clause_store::ClauseStore;
+use config::SolveConfig;
+use gauss::XorMatrix;
+use portfolio::ClauseExchange;
 
 pub struct Solve {
     conflicts: usize,
     conflicts_since_last_forget: usize,
     conflicts_since_last_reset: usize,
     restarts: usize,
+    /// The count of literals propagated
+    propagations: usize,
     pub variables: Vec<Variable>,
-    pub valuation: Vec<Option<bool>>,
+    pub valuation: ValuationVec,
+    /// The polarity of each choice for a variable with a saved phase, from the valuation before a restart or from a local search
+    saved_phases: Option<ValuationVec>,
+    /// For each variable, the level at which the variable was valued, if valued
+    pub decision_levels: Vec<Option<LevelIndex>>,
+    /// For each variable, the activity of the variable
+    pub activity: Vec<ActivityRep>,
+    /// For each literal, the clauses which watch the literal, with a blocker literal from the clause
+    pub watches: Vec<Vec<(ClauseKey, Literal)>>,
+    /// For each literal, the binary clauses containing the literal, with the other literal of the clause
+    pub binary_watches: Vec<Vec<(ClauseKey, Literal)>>,
+    /// For each literal, the cardinality constraints which watch the literal
+    pub cardinality_watches: Vec<Vec<ClauseKey>>,
     pub levels: Vec<Level>,
-    pub formula_clauses: ClauseStore,
-    pub learnt_clauses: ClauseStore,
-    pub watch_q: VecDeque<(Literal, LiteralSource)>,
+    pub clause_store: ClauseStore,
+    /// The XOR constraints of the solve
+    pub xors: XorMatrix,
+    /// Explanations from the XOR constraints, with the level at which each explanation was made
+    xor_reasons: Vec<(LevelIndex, ClauseVec)>,
+    pub trail: Vec<Literal>,
+    q_head: usize,
+    /// Whether some clause is false at level 0, in which case the solve is unsatisfiable
+    inconsistent: bool,
+    /// Variables to be chosen before any other variable
+    prioritised: Vec<VariableId>,
+    /// Literals to be chosen, in order, before any other choice is made
+    assumptions: Vec<Literal>,
+    /// If a solve under assumptions is unsatisfiable, the assumptions used to show this
+    failed_assumptions: Vec<Literal>,
+    pub config: SolveConfig,
+    /// A flag which, once raised, stops the solve
+    interrupt: Option<Arc<AtomicBool>>,
+    /// The exchange of learnt clauses with other solves, if the solve is part of a portfolio
+    exchange: Option<ClauseExchange>,
+    /// A callback asked whether the solve should stop
+    terminate: Option<TerminateCallback>,
+    /// A callback given each learnt clause which passes the filter of the callback
+    learn: Option<LearnHook>,
+    /// Whether the search has been stopped, and the limits of the search
+    stop: StopState,
+    /// The count of clauses and cardinality constraints added as part of the formula, and so the next original index
+    original_count: u32,
+    /// If the solve is inconsistent, the original indices of the clauses used to show this
+    refutation: Option<Vec<u32>>,
+    /// Whether each learnt clause notes the clauses the clause was learnt from
+    origins_tracked: bool,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum SolveStatus {
     AssertingClause,
     NoSolution,
+    /// The solve was stopped before the conflict was analysed
+    Stopped,
 }
 
 pub enum SolveResult {
@@ -39,30 +104,35 @@ pub enum SolveResult {
     Unknown,
 }
 
-pub fn retreive<'a>(
-    formula: &'a ClauseStore,
-    learnt: &'a ClauseStore,
-    key: ClauseKey,
-) -> &'a StoredClause {
-    match key {
-        ClauseKey::Formula(key) => &formula[key],
-        ClauseKey::Learnt(key) => &learnt[key],
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClauseKey {
+    Formula(u32),
+    Learnt(u32),
+    /// A cardinality constraint of the formula
+    Cardinality(u32),
+    /// An explanation from the XOR constraints, which is not kept in the clause store
+    Xor(u32),
 }
 
-pub fn retreive_mut<'a>(
-    formula: &'a mut ClauseStore,
-    learnt: &'a mut ClauseStore,
-    key: ClauseKey,
-) -> Option<&'a mut StoredClause> {
-    match key {
-        ClauseKey::Formula(key) => formula.get_mut(key),
-        ClauseKey::Learnt(key) => learnt.get_mut(key),
+impl ClauseKey {
+    /// The offset of the clause in the clause store, or the index of an explanation from the XOR constraints
+    pub fn offset(&self) -> u32 {
+        match self {
+            ClauseKey::Formula(offset)
+            | ClauseKey::Learnt(offset)
+            | ClauseKey::Cardinality(offset)
+            | ClauseKey::Xor(offset) => *offset,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum ClauseKey {
-    Formula(slotmap::DefaultKey),
-    Learnt(slotmap::DefaultKey),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Solve>();
+    }
 }
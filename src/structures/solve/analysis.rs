@@ -1,87 +1,118 @@
+use crate::errors::SolverError;
 use crate::procedures::resolve_sorted_clauses;
-use crate::structures::valuation::Valuation;
 use crate::structures::{
-    clause::{
-        clause_vec::ClauseVec,
-        stored_clause::{ClauseSource, StoredClause},
-        Clause,
-    },
+    clause::{clause_vec::ClauseVec, stored_clause::ClauseSource, Clause},
+    level::LevelIndex,
     literal::{Literal, LiteralSource},
-    solve::{config, retreive, the_solve::literal_update, ClauseKey, Solve, SolveStatus},
-    variable::Variable,
+    solve::{
+        config,
+        the_solve::{literal_update, literal_update_at},
+        ClauseKey, Solve, SolveStatus,
+    },
+    valuation::Valuation,
 };
 
-use std::collections::VecDeque;
-
 impl Solve {
-    pub fn attempt_fix(&mut self, clause_key: ClauseKey) -> SolveStatus {
-        let conflict_clause = retreive(&self.formula_clauses, &self.learnt_clauses, clause_key);
+    /// Analyses the conflict at the level of the conflict, and learns a clause which asserts a literal.
+    ///
+    /// With chronological backtracking the solve may backtrack a single level rather than backjump to the level of the asserted literal, though the literal is still given the level at which it is asserted.
+    pub fn attempt_fix(&mut self, clause_key: ClauseKey) -> Result<SolveStatus, SolverError> {
+        // as a literal may be implied below the current level, the conflict may be below the current level
+        let conflict_level = {
+            let conflict_clause = self.explanation(clause_key, None);
+            log::trace!(
+                "Fix on clause {} @ {}",
+                conflict_clause.as_string(),
+                self.current_level().index()
+            );
+            conflict_clause
+                .iter()
+                .filter_map(|literal| self.decision_levels[literal.v_id() as usize])
+                .max()
+                .unwrap_or(0)
+        };
+
+        match conflict_level {
+            0 => {
+                self.refute(clause_key);
+                Ok(SolveStatus::NoSolution)
+            }
+            _ if self.stop_requested() => Ok(SolveStatus::Stopped),
+            _ => {
+                // the conflict is analysed at the level of the conflict
+                self.backjump(conflict_level);
 
-        {
-            let level = self.current_level().index();
-            log::trace!("Fix on clause {conflict_clause} @ {level}");
-        }
+                let Some((asserting_clause, origins, assertion)) =
+                    self.conflict_analysis(clause_key)?
+                else {
+                    return Ok(SolveStatus::Stopped);
+                };
+                if !self.config.track_core {
+                    self.origins_tracked = false;
+                }
 
-        match self.current_level().index() {
-            0 => SolveStatus::NoSolution,
-            _ => {
-                let (asserting_clause, clause_source, assertion) =
-                    self.conflict_analysis(conflict_clause);
+                let assertion_level = decision_level(&self.decision_levels, &asserting_clause)?;
+                let backjump_level = match self.config.chronological_backtracking {
+                    Some(limit) if conflict_level - assertion_level > limit => conflict_level - 1,
+                    _ => assertion_level,
+                };
 
                 if asserting_clause.len() == 1 {
-                    self.backjump(0);
+                    self.export_clause(&asserting_clause, 1);
+                    self.note_learnt(&asserting_clause, 1);
+                    self.backjump(backjump_level);
 
-                    let literal_source = match clause_source {
-                        ClauseSource::Resolution(resolution_vector) => {
-                            LiteralSource::Resolution(resolution_vector)
-                        }
-                        _ => panic!("Analysis without resolution"),
-                    };
                     literal_update(
                         assertion,
-                        literal_source.clone(),
+                        LiteralSource::Resolution(origins),
                         &mut self.levels,
-                        &self.variables,
+                        &mut self.decision_levels,
                         &mut self.valuation,
-                        &mut self.formula_clauses,
-                        &mut self.learnt_clauses,
-                    );
-                    self.watch_q.push_back((assertion, literal_source));
+                        &mut self.trail,
+                    )?;
                 } else {
-                    self.backjump(decision_level(&self.variables, asserting_clause.literals()));
-
-                    let clause_key = self.store_clause(asserting_clause, clause_source);
-                    let stored_clause =
-                        retreive(&self.formula_clauses, &self.learnt_clauses, clause_key);
+                    self.backjump(backjump_level);
+
+                    let clause_key =
+                        self.store_clause(asserting_clause, ClauseSource::Resolution(origins))?;
+                    let lbd = self
+                        .clause_store
+                        .retreive(clause_key)
+                        .lbd(&self.decision_levels);
+                    self.clause_store.set_lbd(clause_key, lbd);
+                    if self.exchange.is_some() || self.learn.is_some() {
+                        let learnt = self.clause_store.retreive(clause_key).clause_clone();
+                        self.export_clause(&learnt, lbd);
+                        self.note_learnt(&learnt, lbd);
+                    }
 
                     let anticipated_literal_source = LiteralSource::StoredClause(clause_key);
 
-                    stored_clause.set_lbd(&self.variables);
-
-                    literal_update(
+                    literal_update_at(
                         assertion,
-                        anticipated_literal_source.clone(),
+                        anticipated_literal_source,
+                        assertion_level,
                         &mut self.levels,
-                        &self.variables,
+                        &mut self.decision_levels,
                         &mut self.valuation,
-                        &mut self.formula_clauses,
-                        &mut self.learnt_clauses,
-                    );
-                    self.watch_q
-                        .push_back((assertion, anticipated_literal_source));
+                        &mut self.trail,
+                    )?;
                 }
 
-                SolveStatus::AssertingClause
+                Ok(SolveStatus::AssertingClause)
             }
         }
     }
 
     /// Simple analysis performs resolution on any clause used to obtain a conflict literal at the current decision
+    ///
+    /// Returns the resolved clause, the origins of the resolved clause if the core is tracked, and the literal asserted by the resolved clause.
+    /// Or, returns nothing if the solve is stopped during the analysis.
     pub fn conflict_analysis(
-        &self,
-        conflict_clause: &StoredClause,
-    ) -> (ClauseVec, ClauseSource, Literal) {
-        let mut resolved_clause = conflict_clause.clause_clone();
+        &mut self,
+        conflict_key: ClauseKey,
+    ) -> Result<Option<(ClauseVec, Vec<u32>, Literal)>, SolverError> {
+        let mut resolved_clause = self.explanation(conflict_key, None);
         let mut resolution_trail = vec![];
 
         let previous_level_val = self.valuation_at(self.current_level().index() - 1);
@@ -95,7 +126,11 @@ impl Solve {
         let mut x = self.current_level().observations.clone();
         x.reverse();
 
-        for (src, literal) in &x {
+        for (step, (src, literal)) in x.iter().enumerate() {
+            if self.stop_requested_every(step + 1) {
+                return Ok(None);
+            }
+
             match stopping_criteria {
                 config::StoppingCriteria::FirstAssertingUIP => {
                     if let Some(asserted) = resolved_clause.asserts(&previous_level_val) {
@@ -107,44 +142,56 @@ impl Solve {
             }
 
             if let LiteralSource::StoredClause(clause_key) = src {
-                let stored_source_clause =
-                    retreive(&self.formula_clauses, &self.learnt_clauses, *clause_key);
+                let stored_source_clause = self.explanation(*clause_key, Some(*literal));
 
                 for involved_literal in stored_source_clause.literals() {
-                    used_variables[involved_literal.v_id()] = true;
+                    used_variables[involved_literal.v_id() as usize] = true;
                 }
 
                 let for_the_borrow_checker = resolved_clause.clone();
                 let resolution_result = resolve_sorted_clauses(
                     for_the_borrow_checker.literals(),
                     stored_source_clause.literals(),
-                    literal.v_id,
+                    literal.v_id(),
                 );
                 if let Some(resolution) = resolution_result {
-                    resolution_trail.push(*clause_key);
+                    // an explanation from the XOR constraints is not kept, and so is not an origin of the clause
+                    if !matches!(clause_key, ClauseKey::Xor(_)) {
+                        resolution_trail.push(*clause_key);
+                    }
                     resolved_clause = resolution.to_vec();
                 };
             }
         }
 
-        if asserted_literal.is_none() {
-            if let Some(asserted) = resolved_clause.asserts(&previous_level_val) {
-                asserted_literal = Some(asserted);
-            } else {
-                for x in resolution_trail {
-                    let cls = retreive(&self.formula_clauses, &self.learnt_clauses, x);
-                    println!("{}", cls.as_string());
-                }
-                for ob in self.current_level().observations() {
-                    println!("OBS {:?}", ob);
+        let asserted_literal = match asserted_literal {
+            Some(literal) => literal,
+            None => match resolved_clause.asserts(&previous_level_val) {
+                Some(literal) => literal,
+                None => {
+                    return Err(SolverError::NoAssertion {
+                        level: self.current_level().index(),
+                        conflict: conflict_key,
+                        resolution_trail,
+                        resolved_clause,
+                    })
                 }
-
-                println!("CC {}", conflict_clause.as_string());
-                println!("RC {}", resolved_clause.as_string());
-                println!("PV {}", previous_level_val.as_internal_string());
-                println!("CV {}", self.valuation.as_internal_string());
-                panic!("No assertion…")
+            },
+        };
+
+        let mut origins = vec![];
+        if self.config.track_core {
+            let fixed = resolved_clause
+                .iter()
+                .filter(|literal| self.decision_levels[literal.v_id() as usize] == Some(0))
+                .copied()
+                .collect::<ClauseVec>();
+            origins = self.level_zero_origins(&fixed);
+            for key in std::iter::once(&conflict_key).chain(&resolution_trail) {
+                origins.extend(self.origins(*key));
             }
+            origins.sort_unstable();
+            origins.dedup();
         }
 
         /*
@@ -161,76 +208,88 @@ impl Solve {
         }
 
         // for variable in resolved_clause.variables() {
-        //     self.variables[variable].add_activity(config::ACTIVITY_CONFLICT);
+        //     self.activity[variable] += config::ACTIVITY_CONFLICT;
         // }
-        #[allow(clippy::needless_range_loop)]
-        for index in 0..variable_count {
-            if used_variables[index] {
-                self.variables[index].add_activity(config::ACTIVITY_CONFLICT);
+        for (activity, used) in self.activity.iter_mut().zip(used_variables) {
+            if used {
+                *activity += config::ACTIVITY_CONFLICT;
             }
         }
 
-        (
-            resolved_clause,
-            ClauseSource::Resolution(resolution_trail),
-            asserted_literal.unwrap(),
-        )
+        Ok(Some((resolved_clause, origins, asserted_literal)))
     }
 
-    pub fn core(&self) {
-        println!();
-        println!("c An unsatisfiable core of the original formula:\n");
-        let node_indicies = self.levels[0]
-            .observations()
-            .iter()
-            .filter_map(|(source, _)| match source {
-                LiteralSource::StoredClause(weak) => Some(*weak),
-                _ => None,
-            });
-        let node_indicies_vec = node_indicies.collect::<Vec<_>>();
-        let simple_core = self.extant_origins(node_indicies_vec);
-        for clause in simple_core {
-            println!("{}", clause.as_dimacs(&self.variables))
+    /// The clause which explains the given literal, or the conflict if no literal is given, ordered as a formula clause.
+    ///
+    /// A clause is its own explanation.
+    /// For a cardinality constraint, the explanation is the literal together with each literal of the constraint which is false, as any literal of the constraint which is not false was implied alongside the literal.
+    /// For the XOR constraints, the explanation was made when the literal or conflict was found.
+    pub fn explanation(&self, key: ClauseKey, implied: Option<Literal>) -> ClauseVec {
+        match key {
+            ClauseKey::Formula(_) | ClauseKey::Learnt(_) => {
+                self.clause_store.retreive(key).sorted_clause()
+            }
+            ClauseKey::Xor(index) => self.xor_reasons[index as usize].1.clone(),
+            ClauseKey::Cardinality(_) => {
+                let mut the_clause = self
+                    .clause_store
+                    .retreive(key)
+                    .literals()
+                    .filter(|literal| self.valuation.of_literal(*literal) == Some(false))
+                    .chain(implied)
+                    .collect::<ClauseVec>();
+                the_clause.sort_unstable();
+                the_clause
+            }
         }
-        println!();
     }
 
-    pub fn extant_origins(&self, clauses: Vec<ClauseKey>) -> Vec<&StoredClause> {
-        #[allow(clippy::mutable_key_type)]
-        let mut origin_nodes = vec![];
-
-        let mut q = VecDeque::new();
-        for clause in clauses {
-            q.push_back(clause);
-        }
-        loop {
-            if q.is_empty() {
-                break;
-            }
-
-            let clause_key = q.pop_front().expect("Ah, the queue was empty…");
-            let stored_clause = retreive(&self.formula_clauses, &self.learnt_clauses, clause_key);
+    /// The level at which the literal is implied by the clause of the key, which is the greatest level of the other literals of the explanation of the literal.
+    pub(super) fn implication_level(&self, key: ClauseKey, implied: Literal) -> LevelIndex {
+        self.explanation(key, Some(implied))
+            .iter()
+            .filter(|literal| **literal != implied)
+            .filter_map(|literal| self.decision_levels[literal.v_id() as usize])
+            .max()
+            .unwrap_or(0)
+    }
 
-            match stored_clause.source() {
-                ClauseSource::Resolution(origins) => {
-                    for antecedent in origins {
-                        q.push_back(*antecedent);
+    /// The assumptions which, together with the clauses of the solve, imply the negation of the given assumption, and the assumption itself.
+    ///
+    /// As assumptions are chosen before any other literal, the choice of each level is an assumption.
+    pub fn analyze_final(&self, failed: Literal) -> Vec<Literal> {
+        let mut seen = vec![false; self.variables.len()];
+        seen[failed.v_id() as usize] = true;
+        let mut failed_assumptions = vec![failed];
+
+        for level in self.levels.iter().skip(1).rev() {
+            for (source, literal) in level.observations().iter().rev() {
+                if seen[literal.v_id() as usize] {
+                    if let LiteralSource::StoredClause(key) = source {
+                        for v_id in self.explanation(*key, Some(*literal)).variables() {
+                            seen[v_id as usize] = true;
+                        }
                     }
                 }
-                ClauseSource::Formula => {
-                    origin_nodes.push(stored_clause);
+            }
+            if let Some(choice) = level.choice {
+                if seen[choice.v_id() as usize] && choice != failed {
+                    failed_assumptions.push(choice);
                 }
             }
         }
-        origin_nodes
+        failed_assumptions
     }
 }
 
 /// Either the most recent decision level in the resolution clause prior to the current level or 0.
-fn decision_level(variables: &[Variable], literals: impl Iterator<Item = Literal>) -> usize {
+fn decision_level(
+    decision_levels: &[Option<LevelIndex>],
+    clause: &ClauseVec,
+) -> Result<LevelIndex, SolverError> {
     let mut top_two = (None, None);
-    for lit in literals {
-        if let Some(dl) = unsafe { (*variables.get_unchecked(lit.v_id())).decision_level() } {
+    for lit in clause.literals() {
+        if let Some(dl) = unsafe { *decision_levels.get_unchecked(lit.v_id() as usize) } {
             if top_two.1.is_none() {
                 top_two.1 = Some(dl)
             } else if top_two.1.is_some_and(|t1| dl > t1) {
@@ -243,8 +302,41 @@ fn decision_level(variables: &[Variable], literals: impl Iterator<Item = Literal
     }
 
     match top_two {
-        (None, Some(_)) => 0,
-        (Some(x), Some(_)) => x,
-        _ => panic!("Decision level issue: {:?}", top_two),
+        (None, Some(_)) => Ok(0),
+        (Some(x), Some(_)) => Ok(x),
+        _ => Err(SolverError::DecisionLevel {
+            clause: clause.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::solve::{
+        config::SolveConfig, test_formulas::random_formula, SolveResult,
+    };
+
+    #[test]
+    fn chronological_backtracking() {
+        for seed in 1..=12 {
+            let formula = random_formula(60, 260, seed);
+
+            let mut the_solve = Solve::from_formula(formula.clone()).unwrap();
+            let expected = the_solve.do_solve().unwrap().0;
+
+            let mut the_solve = Solve::from_formula(formula).unwrap();
+            the_solve.set_config(SolveConfig {
+                chronological_backtracking: Some(0),
+                ..SolveConfig::default()
+            });
+            match (expected, the_solve.do_solve().unwrap().0) {
+                (SolveResult::Satisfiable, SolveResult::Satisfiable) => assert!(the_solve
+                    .stored_clauses()
+                    .all(|stored_clause| stored_clause.is_sat_on(&the_solve.valuation))),
+                (SolveResult::Unsatisfiable, SolveResult::Unsatisfiable) => {}
+                _ => panic!("Results differ for seed {seed}"),
+            }
+        }
     }
 }
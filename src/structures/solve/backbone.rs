@@ -0,0 +1,102 @@
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::{clause_vec::ClauseVec, stored_clause::ClauseSource},
+        literal::Literal,
+        solve::{Solve, SolveResult},
+        valuation::Valuation,
+        variable::VariableId,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackboneResult {
+    /// The literals true in every model, ordered by variable
+    Backbone(ClauseVec),
+    Unsatisfiable,
+    Unknown,
+}
+
+impl Solve {
+    /// Finds the literals which are true in every model of the solve.
+    ///
+    /// Each literal of a first model is a candidate, and a candidate is in the backbone if the solve is unsatisfiable under the negation of the candidate.
+    /// Otherwise, each candidate false on the new model is dropped.
+    /// As a pure literal may be false in some model, no pure literal should be chosen before the backbone is found.
+    pub fn backbone(&mut self) -> Result<BackboneResult, SolverError> {
+        match self.do_solve()?.0 {
+            SolveResult::Satisfiable => {}
+            SolveResult::Unsatisfiable => return Ok(BackboneResult::Unsatisfiable),
+            SolveResult::Unknown => return Ok(BackboneResult::Unknown),
+        }
+
+        let mut backbone = vec![];
+        let mut candidates = vec![];
+        for v_id in 0..self.variables.len() as VariableId {
+            if let Some(value) = self.valuation.of_v_id(v_id) {
+                let literal = Literal::new(v_id, value);
+                match self.decision_levels[v_id as usize] {
+                    Some(0) => backbone.push(literal),
+                    _ => candidates.push(Some(literal)),
+                }
+            }
+        }
+
+        for index in 0..candidates.len() {
+            let Some(candidate) = candidates[index] else {
+                continue;
+            };
+            match self.solve_under_assumptions(&[candidate.negate()])?.0 {
+                SolveResult::Satisfiable => {
+                    for other in candidates.iter_mut().skip(index) {
+                        if other.is_some_and(|literal| {
+                            self.valuation.of_literal(literal) == Some(false)
+                        }) {
+                            *other = None;
+                        }
+                    }
+                }
+                SolveResult::Unsatisfiable => {
+                    // the candidate is added as a unit clause to simplify each later solve
+                    backbone.push(candidate);
+                    self.add_clause(vec![candidate], ClauseSource::Formula)?;
+                }
+                SolveResult::Unknown => return Ok(BackboneResult::Unknown),
+            }
+        }
+
+        backbone.sort_unstable();
+        Ok(BackboneResult::Backbone(backbone))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::solve::test_formulas::solve_of;
+
+    #[test]
+    fn backbone_literals() {
+        // 2 and 6 are required, so 3 is false and 4 is true, while 1 is free
+        let mut the_solve = solve_of("1 2 0\n-1 2 0\n3 4 0\n6 0\n-6 -3 0\n");
+        let backbone = match the_solve.backbone().unwrap() {
+            BackboneResult::Backbone(backbone) => backbone,
+            _ => panic!("No backbone found"),
+        };
+        let mut named = backbone
+            .iter()
+            .map(|literal| {
+                let name = the_solve.variables[literal.v_id() as usize].name();
+                match literal.polarity() {
+                    true => name.to_string(),
+                    false => format!("-{name}"),
+                }
+            })
+            .collect::<Vec<_>>();
+        named.sort();
+        assert_eq!(named, vec!["-3", "2", "4", "6"]);
+
+        let mut the_solve = solve_of("1 0\n-1 0\n");
+        assert_eq!(the_solve.backbone().unwrap(), BackboneResult::Unsatisfiable);
+    }
+}
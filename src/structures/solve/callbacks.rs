@@ -0,0 +1,251 @@
+use crate::structures::{literal::Literal, solve::Solve};
+
+/// The count of steps, such as literals propagated, between each time the terminate callback is asked whether the solve should stop
+const TERMINATE_INTERVAL: usize = 1024;
+
+/// A callback asked on occasion whether the solve should stop, with the solve stopping if the callback returns true
+pub type TerminateCallback = Box<dyn FnMut() -> bool + Send>;
+
+/// A callback given each clause learnt by the solve, together with the lbd of the clause
+pub type LearnCallback = Box<dyn FnMut(&[Literal], usize) + Send>;
+
+/// A learn callback, together with the greatest length and lbd of a clause given to the callback
+pub struct LearnHook {
+    max_length: usize,
+    max_lbd: usize,
+    callback: LearnCallback,
+}
+
+/// Whether a search is stopped, together with the limits of the search from the budgets of the configuration.
+///
+/// A stopped solve returns an unknown result, and as the next solve begins with a backjump to level 0 may be solved again.
+#[derive(Default)]
+pub struct StopState {
+    /// Whether a search is in progress, as checks are only made during a search
+    searching: bool,
+    stopped: bool,
+    /// The count of conflicts at which the search stops
+    conflict_limit: Option<usize>,
+    /// The count of propagated literals at which the search stops
+    propagation_limit: Option<usize>,
+}
+
+impl Solve {
+    /// Sets a callback asked during a solve whether the solve should stop, or removes the callback if none is given.
+    /// A solve which stops returns an unknown result.
+    pub fn set_terminate(&mut self, callback: Option<TerminateCallback>) {
+        self.terminate = callback;
+    }
+
+    /// Sets a callback given each learnt clause of at most the given length and lbd, or removes the callback if none is given.
+    ///
+    /// A learnt unit clause has an lbd of 1.
+    pub fn set_learn(
+        &mut self,
+        max_length: usize,
+        max_lbd: usize,
+        callback: Option<LearnCallback>,
+    ) {
+        self.learn = callback.map(|callback| LearnHook {
+            max_length,
+            max_lbd,
+            callback,
+        });
+    }
+
+    /// Whether the terminate callback, if any, asks the solve to stop.
+    pub fn terminate_requested(&mut self) -> bool {
+        match &mut self.terminate {
+            Some(callback) => callback(),
+            None => false,
+        }
+    }
+
+    /// Gives the learnt clause to the learn callback, if any, and if the clause is short enough and of low enough lbd.
+    pub fn note_learnt(&mut self, clause: &[Literal], lbd: usize) {
+        if let Some(hook) = &mut self.learn {
+            if clause.len() <= hook.max_length && lbd <= hook.max_lbd {
+                (hook.callback)(clause, lbd);
+            }
+        }
+    }
+
+    /// Begins a search, with limits from the budgets of the configuration.
+    pub(super) fn begin_search(&mut self) {
+        self.stop = StopState {
+            searching: true,
+            stopped: false,
+            conflict_limit: self
+                .config
+                .conflict_budget
+                .map(|budget| self.conflicts + budget),
+            propagation_limit: self
+                .config
+                .propagation_budget
+                .map(|budget| self.propagations + budget),
+        };
+    }
+
+    pub(super) fn end_search(&mut self) {
+        self.stop = StopState::default();
+    }
+
+    /// Whether the search has been stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.stopped
+    }
+
+    /// Whether the search should stop, due to the interrupt flag, the terminate callback, or a budget.
+    /// Once true, remains true until the search ends.
+    ///
+    /// Checked on each iteration of a solve, each literal propagated, each resolution of an analysis, and before the clause store is compacted.
+    /// An analysis which stops learns nothing, and propagation only stops between literals.
+    pub fn stop_requested(&mut self) -> bool {
+        if self.stop.searching && !self.stop.stopped {
+            self.stop.stopped =
+                self.is_interrupted() || self.budget_exhausted() || self.terminate_requested();
+        }
+        self.stop.stopped
+    }
+
+    /// As [Solve::stop_requested], though with the terminate callback only asked once for each interval of steps, given the count of steps taken.
+    pub(super) fn stop_requested_every(&mut self, steps: usize) -> bool {
+        if self.stop.searching && !self.stop.stopped {
+            self.stop.stopped = self.is_interrupted()
+                || self.budget_exhausted()
+                || (steps.is_multiple_of(TERMINATE_INTERVAL) && self.terminate_requested());
+        }
+        self.stop.stopped
+    }
+
+    /// As [Solve::stop_requested_every], with each propagated literal a step.
+    pub(super) fn stop_requested_in_propagation(&mut self) -> bool {
+        self.stop_requested_every(self.propagations)
+    }
+
+    fn budget_exhausted(&self) -> bool {
+        self.stop
+            .conflict_limit
+            .is_some_and(|limit| self.conflicts >= limit)
+            || self
+                .stop
+                .propagation_limit
+                .is_some_and(|limit| self.propagations >= limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
+
+    use crate::structures::{
+        literal::LiteralSource,
+        solve::{
+            config::SolveConfig, test_formulas::pigeonhole, the_solve::literal_update, SolveResult,
+        },
+        valuation::Valuation,
+    };
+
+    #[test]
+    fn learnt_clauses_are_filtered() {
+        let mut the_solve = Solve::from_formula(pigeonhole(4)).unwrap();
+        let learnt = Arc::new(Mutex::new(vec![]));
+        let noted = learnt.clone();
+        the_solve.set_learn(
+            6,
+            3,
+            Some(Box::new(move |clause: &[Literal], lbd: usize| {
+                noted.lock().unwrap().push((clause.to_vec(), lbd))
+            })),
+        );
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+
+        let learnt = learnt.lock().unwrap();
+        assert!(!learnt.is_empty());
+        assert!(learnt
+            .iter()
+            .all(|(clause, lbd)| !clause.is_empty() && clause.len() <= 6 && *lbd <= 3));
+    }
+
+    #[test]
+    fn stopped_solves_are_reusable() {
+        let mut the_solve = Solve::from_formula(pigeonhole(5)).unwrap();
+
+        the_solve.set_config(SolveConfig {
+            conflict_budget: Some(10),
+            ..SolveConfig::default()
+        });
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unknown
+        ));
+
+        the_solve.set_config(SolveConfig {
+            propagation_budget: Some(100),
+            ..SolveConfig::default()
+        });
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unknown
+        ));
+
+        the_solve.set_config(SolveConfig::default());
+        the_solve.set_terminate(Some(Box::new(|| true)));
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unknown
+        ));
+
+        the_solve.set_terminate(None);
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+    }
+
+    #[test]
+    fn analysis_stops_when_interrupted() {
+        let mut the_solve = Solve::from_formula(pigeonhole(3)).unwrap();
+
+        // each unvalued variable is chosen true until a conflict is found
+        let mut conflict = None;
+        for v_id in 0..the_solve.variables.len() as u32 {
+            let literal = Literal::new(v_id, true);
+            if the_solve.valuation.of_literal(literal).is_some() {
+                continue;
+            }
+            the_solve.add_fresh_level();
+            literal_update(
+                literal,
+                LiteralSource::Choice,
+                &mut the_solve.levels,
+                &mut the_solve.decision_levels,
+                &mut the_solve.valuation,
+                &mut the_solve.trail,
+            )
+            .unwrap();
+            conflict = the_solve.propagate().unwrap();
+            if conflict.is_some() {
+                break;
+            }
+        }
+        let conflict = conflict.unwrap();
+
+        let flag = Arc::new(AtomicBool::new(true));
+        the_solve.set_interrupt(flag.clone());
+        the_solve.begin_search();
+        assert!(the_solve.conflict_analysis(conflict).unwrap().is_none());
+        the_solve.end_search();
+
+        flag.store(false, Ordering::Relaxed);
+        the_solve.begin_search();
+        assert!(the_solve.conflict_analysis(conflict).unwrap().is_some());
+    }
+}
@@ -1 +1,403 @@
+use crate::structures::{
+    clause::{
+        clause_vec::ClauseVec,
+        stored_clause::{get_status, ClauseSource, StoredClause, Watch, WatchStatus, WatchUpdate},
+    },
+    literal::Literal,
+    solve::ClauseKey,
+    valuation::Valuation,
+};
 
+/// The words before the literals of a clause: the length and flags, the lbd, and either the offset of the origins or the original index.
+const HEADER_LENGTH: usize = 3;
+
+const LEARNT_FLAG: u32 = 1 << 31;
+const DELETED_FLAG: u32 = 1 << 30;
+const CARDINALITY_FLAG: u32 = 1 << 29;
+const LENGTH_MASK: u32 = !(LEARNT_FLAG | DELETED_FLAG | CARDINALITY_FLAG);
+
+const NO_SOURCE: u32 = u32::MAX;
+
+/// Clauses stored contiguously in an arena of words, each identified by the offset of its header.
+///
+/// The watched literals of a clause are the first two, or the first `degree + 1` of a cardinality constraint, whose degree is kept in place of the lbd.
+/// A deleted clause remains until the arena is compacted, after which any key may have changed.
+#[derive(Default)]
+pub struct ClauseStore {
+    arena: Vec<u32>,
+    /// The origins of learnt clauses, each as a count followed by the original indices
+    origins: Vec<u32>,
+    learnt_count: usize,
+    garbage: usize,
+}
+
+/// The offsets of each clause which survived compaction, before and after.
+pub struct Relocation {
+    moves: Vec<(u32, u32)>,
+}
+
+impl Relocation {
+    /// The key to the clause after compaction, if the clause survived.
+    /// An explanation from the XOR constraints is not in the store, and so is not moved.
+    pub fn relocate(&self, key: ClauseKey) -> Option<ClauseKey> {
+        if let ClauseKey::Xor(_) = key {
+            return Some(key);
+        }
+        let offset = key.offset();
+        match self.moves.binary_search_by(|(from, _)| from.cmp(&offset)) {
+            Ok(index) => {
+                let to = self.moves[index].1;
+                match key {
+                    ClauseKey::Formula(_) => Some(ClauseKey::Formula(to)),
+                    ClauseKey::Learnt(_) => Some(ClauseKey::Learnt(to)),
+                    ClauseKey::Cardinality(_) => Some(ClauseKey::Cardinality(to)),
+                    ClauseKey::Xor(_) => Some(key),
+                }
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+impl ClauseStore {
+    pub fn new() -> Self {
+        ClauseStore {
+            arena: vec![],
+            origins: vec![],
+            learnt_count: 0,
+            garbage: 0,
+        }
+    }
+
+    /// Stores the clause with the literals in the given order, so the first two literals are watched.
+    pub fn insert(&mut self, clause: &ClauseVec, source: ClauseSource) -> ClauseKey {
+        let offset = self.arena.len() as u32;
+        let length = clause.len() as u32;
+
+        let key = match source {
+            ClauseSource::Formula => {
+                self.arena.extend([length, 0, NO_SOURCE]);
+                ClauseKey::Formula(offset)
+            }
+            ClauseSource::Resolution(origins) => {
+                let origins_offset = match origins.is_empty() {
+                    true => NO_SOURCE,
+                    false => {
+                        let origins_offset = self.origins.len() as u32;
+                        self.origins.push(origins.len() as u32);
+                        self.origins.extend(origins);
+                        origins_offset
+                    }
+                };
+                self.arena.extend([length | LEARNT_FLAG, 0, origins_offset]);
+                self.learnt_count += 1;
+                ClauseKey::Learnt(offset)
+            }
+        };
+        self.arena
+            .extend(clause.iter().map(|literal| literal.code()));
+        key
+    }
+
+    /// Stores the constraint that at least `degree` of the literals are true, with the literals in the given order, so the first `degree + 1` literals are watched.
+    pub fn insert_cardinality(&mut self, literals: &ClauseVec, degree: usize) -> ClauseKey {
+        let offset = self.arena.len() as u32;
+        self.arena.extend([
+            literals.len() as u32 | CARDINALITY_FLAG,
+            degree as u32,
+            NO_SOURCE,
+        ]);
+        self.arena
+            .extend(literals.iter().map(|literal| literal.code()));
+        ClauseKey::Cardinality(offset)
+    }
+
+    pub fn retreive(&self, key: ClauseKey) -> StoredClause<'_> {
+        let offset = key.offset() as usize;
+        let header = &self.arena[offset..offset + HEADER_LENGTH];
+        let length = (header[0] & LENGTH_MASK) as usize;
+        let origins = match (header[0] & LEARNT_FLAG, header[2]) {
+            (0, _) | (_, NO_SOURCE) => &[],
+            (_, origins_offset) => {
+                let start = origins_offset as usize + 1;
+                &self.origins[start..start + self.origins[start - 1] as usize]
+            }
+        };
+        let literals_start = offset + HEADER_LENGTH;
+        StoredClause::new(
+            key,
+            header[1],
+            origins,
+            &self.arena[literals_start..literals_start + length],
+        )
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = StoredClause<'_>> {
+        self.keys().map(|key| self.retreive(key))
+    }
+
+    /// The key of each clause in the store which has not been deleted
+    pub fn keys(&self) -> impl Iterator<Item = ClauseKey> + '_ {
+        let mut offset = 0;
+        std::iter::from_fn(move || {
+            while offset < self.arena.len() {
+                let header = self.arena[offset];
+                let key = if header & LEARNT_FLAG != 0 {
+                    ClauseKey::Learnt(offset as u32)
+                } else if header & CARDINALITY_FLAG != 0 {
+                    ClauseKey::Cardinality(offset as u32)
+                } else {
+                    ClauseKey::Formula(offset as u32)
+                };
+                offset += HEADER_LENGTH + (header & LENGTH_MASK) as usize;
+                if header & DELETED_FLAG == 0 {
+                    return Some(key);
+                }
+            }
+            None
+        })
+    }
+
+    pub fn learnt_count(&self) -> usize {
+        self.learnt_count
+    }
+
+    /// The count of words in the arena used by deleted clauses
+    pub fn garbage(&self) -> usize {
+        self.garbage
+    }
+
+    pub fn length(&self, key: ClauseKey) -> usize {
+        (unsafe { *self.arena.get_unchecked(key.offset() as usize) } & LENGTH_MASK) as usize
+    }
+
+    pub fn is_deleted(&self, key: ClauseKey) -> bool {
+        unsafe { *self.arena.get_unchecked(key.offset() as usize) & DELETED_FLAG != 0 }
+    }
+
+    pub fn lbd(&self, key: ClauseKey) -> usize {
+        unsafe { *self.arena.get_unchecked(key.offset() as usize + 1) as usize }
+    }
+
+    /// The original index of a clause of the formula or cardinality constraint, if noted.
+    pub fn original_index(&self, key: ClauseKey) -> Option<u32> {
+        match key {
+            ClauseKey::Formula(offset) | ClauseKey::Cardinality(offset) => {
+                match self.arena[offset as usize + 2] {
+                    NO_SOURCE => None,
+                    index => Some(index),
+                }
+            }
+            ClauseKey::Learnt(_) | ClauseKey::Xor(_) => None,
+        }
+    }
+
+    /// Notes the original index of a clause of the formula or cardinality constraint.
+    pub fn set_original_index(&mut self, key: ClauseKey, index: u32) {
+        if let ClauseKey::Formula(offset) | ClauseKey::Cardinality(offset) = key {
+            self.arena[offset as usize + 2] = index;
+        }
+    }
+
+    /// The count of literals of the cardinality constraint which must be true
+    pub fn degree(&self, key: ClauseKey) -> usize {
+        self.arena[key.offset() as usize + 1] as usize
+    }
+
+    pub fn set_lbd(&mut self, key: ClauseKey, lbd: usize) {
+        self.arena[key.offset() as usize + 1] = lbd as u32;
+    }
+
+    pub fn get_watched(&self, key: ClauseKey, watch: Watch) -> Literal {
+        let position = key.offset() as usize + HEADER_LENGTH;
+        let code = match watch {
+            Watch::A => unsafe { *self.arena.get_unchecked(position) },
+            Watch::B => unsafe { *self.arena.get_unchecked(position + 1) },
+        };
+        Literal::from_code(code)
+    }
+
+    /// Looks for some literal which is not false on the valuation to replace the given watch.
+    /// If found, the replacement is swapped into the position of the watch.
+    pub fn update_watch(
+        &mut self,
+        key: ClauseKey,
+        watch: Watch,
+        valuation: &impl Valuation,
+    ) -> WatchUpdate {
+        let start = key.offset() as usize + HEADER_LENGTH;
+        let length = self.length(key);
+        let the_wc = &mut self.arena[start..start + length];
+
+        let mut replacement = WatchUpdate::NoUpdate;
+
+        let mut idx = 2;
+        'search_loop: for _ in 2..length {
+            let the_literal = Literal::from_code(unsafe { *the_wc.get_unchecked(idx) });
+            match get_status(the_literal, valuation) {
+                WatchStatus::None | WatchStatus::Witness => {
+                    let the_watch = match watch {
+                        Watch::A => Literal::from_code(the_wc[0]),
+                        Watch::B => Literal::from_code(the_wc[1]),
+                    };
+                    replacement = WatchUpdate::FromTo(the_watch, the_literal);
+                    break 'search_loop;
+                }
+                WatchStatus::Conflict => {
+                    idx += 1;
+                }
+            }
+        }
+
+        if let WatchUpdate::FromTo(_, _) = replacement {
+            let clause_index = match watch {
+                Watch::A => 0,
+                Watch::B => 1,
+            };
+            let mix_up = idx / 4;
+            if mix_up > 2 {
+                the_wc.swap(mix_up, idx);
+                the_wc.swap(clause_index, mix_up);
+            } else {
+                the_wc.swap(clause_index, idx);
+            }
+        }
+
+        replacement
+    }
+
+    /// Looks for some unwatched literal of the cardinality constraint which is not false on the valuation to replace the falsified watch.
+    /// If found, the replacement is swapped into the position of the watch.
+    pub fn update_cardinality_watch(
+        &mut self,
+        key: ClauseKey,
+        falsified: Literal,
+        valuation: &impl Valuation,
+    ) -> WatchUpdate {
+        let start = key.offset() as usize + HEADER_LENGTH;
+        let length = self.length(key);
+        let degree = self.degree(key);
+        let literals = &mut self.arena[start..start + length];
+
+        let watch_index = match literals[..=degree]
+            .iter()
+            .position(|&code| code == falsified.code())
+        {
+            Some(index) => index,
+            None => return WatchUpdate::NoUpdate,
+        };
+
+        let replacement = literals[degree + 1..]
+            .iter()
+            .position(|&code| valuation.of_literal(Literal::from_code(code)) != Some(false));
+        match replacement {
+            Some(offset) => {
+                let index = degree + 1 + offset;
+                literals.swap(watch_index, index);
+                WatchUpdate::FromTo(falsified, Literal::from_code(literals[watch_index]))
+            }
+            None => WatchUpdate::NoUpdate,
+        }
+    }
+
+    /// Marks the clause as deleted, though the clause remains in the arena until compaction.
+    pub fn remove(&mut self, key: ClauseKey) {
+        let offset = key.offset() as usize;
+        if self.arena[offset] & DELETED_FLAG == 0 {
+            self.arena[offset] |= DELETED_FLAG;
+            self.garbage += HEADER_LENGTH + (self.arena[offset] & LENGTH_MASK) as usize;
+            if let ClauseKey::Learnt(_) = key {
+                self.learnt_count -= 1;
+            }
+        }
+    }
+
+    /// Removes deleted clauses from the arena, moving each remaining clause towards the start of the arena.
+    /// As keys are offsets, any key held outside the store must be relocated after compaction.
+    pub fn compact(&mut self) -> Relocation {
+        let mut moves = Vec::with_capacity(self.arena.len() / 4);
+        let mut origins = Vec::with_capacity(self.origins.len());
+
+        let mut read = 0;
+        let mut write = 0;
+        while read < self.arena.len() {
+            let header = self.arena[read];
+            let span = HEADER_LENGTH + (header & LENGTH_MASK) as usize;
+            if header & DELETED_FLAG == 0 {
+                moves.push((read as u32, write as u32));
+                let origins_offset = self.arena[read + 2];
+                if header & LEARNT_FLAG != 0 && origins_offset != NO_SOURCE {
+                    let start = origins_offset as usize;
+                    let end = start + 1 + self.origins[start] as usize;
+                    self.arena[read + 2] = origins.len() as u32;
+                    origins.extend_from_slice(&self.origins[start..end]);
+                }
+                self.arena.copy_within(read..read + span, write);
+                write += span;
+            }
+            read += span;
+        }
+        self.arena.truncate(write);
+        self.garbage = 0;
+
+        // origins are original indices, and so are unchanged by compaction
+        self.origins = origins;
+
+        Relocation { moves }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::clause::Clause;
+
+    #[test]
+    fn compact_relocates() {
+        let mut store = ClauseStore::new();
+        let a = store.insert(
+            &vec![Literal::new(0, true), Literal::new(1, false)],
+            ClauseSource::Formula,
+        );
+        store.set_original_index(a, 4);
+        let b = store.insert(
+            &vec![
+                Literal::new(1, true),
+                Literal::new(2, false),
+                Literal::new(3, false),
+            ],
+            ClauseSource::Resolution(vec![4]),
+        );
+        let c = store.insert(
+            &vec![
+                Literal::new(2, true),
+                Literal::new(3, true),
+                Literal::new(4, false),
+            ],
+            ClauseSource::Resolution(vec![2, 4]),
+        );
+
+        store.remove(b);
+        assert_eq!(store.learnt_count(), 1);
+        assert_eq!(store.keys().collect::<Vec<_>>(), vec![a, c]);
+
+        let relocation = store.compact();
+        assert_eq!(store.garbage(), 0);
+        assert_eq!(relocation.relocate(a), Some(a));
+        assert_eq!(store.original_index(a), Some(4));
+        assert_eq!(relocation.relocate(b), None);
+
+        let relocated_c = relocation.relocate(c).unwrap();
+        assert_eq!(relocated_c, ClauseKey::Learnt(b.offset()));
+        assert_eq!(
+            store.retreive(relocated_c).clause_clone(),
+            vec![
+                Literal::new(2, true),
+                Literal::new(3, true),
+                Literal::new(4, false)
+            ]
+        );
+        assert_eq!(store.retreive(relocated_c).origins(), &[2, 4]);
+        assert_eq!(store.retreive(relocated_c).length(), 3);
+    }
+}
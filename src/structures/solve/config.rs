@@ -12,10 +12,60 @@ pub static mut STOPPING_CRITERIA: StoppingCriteria = StoppingCriteria::FirstAsse
 pub static mut RESTARTS_ALLOWED: bool = true;
 pub static mut HOBSON_CHOICES: bool = false;
 pub static mut TIME_LIMIT: Option<std::time::Duration> = None;
+pub static mut LOCAL_SEARCH: Option<usize> = None;
+pub static mut CHRONOLOGICAL_BACKTRACKING: Option<usize> = None;
+pub static mut TRAIL_REUSE: bool = false;
 
 pub static mut REDUCTION_ALLOWED: bool = false;
 
-use crate::structures::solve::Solve;
+use crate::procedures::xorshift;
+use crate::structures::{
+    level::LevelIndex,
+    solve::Solve,
+    variable::{ActivityRep, VariableId},
+};
+
+/// Configuration which may differ between solves, such as between the workers of a portfolio.
+#[derive(Debug, Clone)]
+pub struct SolveConfig {
+    pub restarts_allowed: bool,
+    /// The count of conflicts for each unit of the luby sequence between restarts
+    pub restart_unit: usize,
+    /// The polarity of a choice for a variable without a previous value
+    pub default_polarity: bool,
+    /// A seed for small random initial activities, with every activity initially zero if the seed is zero
+    pub seed: u64,
+    /// Whether learnt clauses note the clauses of the formula they were learnt from, as required for an unsatisfiable core
+    pub track_core: bool,
+    /// The greatest count of conflicts of each solve, without limit if none
+    pub conflict_budget: Option<usize>,
+    /// The greatest count of propagated literals of each solve, without limit if none
+    pub propagation_budget: Option<usize>,
+    /// The count of restarts between each local search for phases, without local search if none
+    pub local_search: Option<usize>,
+    /// The count of levels above which a backjump is instead a backtrack of a single level, without chronological backtracking if none
+    pub chronological_backtracking: Option<usize>,
+    /// Whether a restart keeps each level with a choice which would be made again, rather than backjumping to level 0
+    pub trail_reuse: bool,
+}
+
+impl Default for SolveConfig {
+    /// The configuration given by the global configuration variables.
+    fn default() -> Self {
+        SolveConfig {
+            restarts_allowed: unsafe { RESTARTS_ALLOWED },
+            restart_unit: 256,
+            default_polarity: false,
+            seed: 0,
+            track_core: unsafe { SHOW_CORE },
+            conflict_budget: None,
+            propagation_budget: None,
+            local_search: unsafe { LOCAL_SEARCH },
+            chronological_backtracking: unsafe { CHRONOLOGICAL_BACKTRACKING },
+            trail_reuse: unsafe { TRAIL_REUSE },
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub enum StoppingCriteria {
@@ -32,7 +82,47 @@ pub enum ExplorationPriority {
 
 impl Solve {
     pub fn it_is_time_to_reduce(&self) -> bool {
-        self.conflicts_since_last_forget >= 256_usize.wrapping_mul(luby(self.restarts + 1))
+        self.conflicts_since_last_forget
+            >= self
+                .config
+                .restart_unit
+                .wrapping_mul(luby(self.restarts + 1))
+    }
+
+    /// The level to backjump to on a restart.
+    ///
+    /// Without trail reuse, this is level 0.
+    /// With trail reuse, each level with a choice preferred to the next choice is kept, as after a backjump to level 0 the choices of these levels would be made again.
+    /// Any level of an assumption is kept, as the assumptions are chosen before any other literal.
+    pub fn restart_level(&self) -> LevelIndex {
+        if !self.config.trail_reuse {
+            return 0;
+        }
+        let next = match self.most_active_none(&self.valuation) {
+            Some(v_id) => v_id as VariableId,
+            None => return self.current_level().index(),
+        };
+        // a choice is preferred to the next choice if prioritised when the next choice is not, or otherwise if more active
+        let less_preferred = |v_id: VariableId| match self
+            .prioritised
+            .contains(&v_id)
+            .cmp(&self.prioritised.contains(&next))
+        {
+            std::cmp::Ordering::Equal => {
+                self.activity[v_id as usize] < self.activity[next as usize]
+            }
+            ordering => ordering.is_lt(),
+        };
+
+        self.levels
+            .iter()
+            .skip(self.assumptions.len() + 1)
+            .find(|level| {
+                level
+                    .choice
+                    .is_some_and(|choice| less_preferred(choice.v_id()))
+            })
+            .map_or(self.current_level().index(), |level| level.index() - 1)
     }
 }
 
@@ -48,3 +138,55 @@ fn luby(i: usize) -> usize {
         k += 1
     }
 }
+
+impl Solve {
+    /// Sets the configuration of the solve, and the initial activity of each variable from the seed of the configuration.
+    pub fn set_config(&mut self, config: SolveConfig) {
+        if config.seed != 0 {
+            // xorshift, as the activities only need to break ties between variables differently for different seeds
+            let mut state = config.seed;
+            for activity in &mut self.activity {
+                *activity =
+                    (xorshift(&mut state) % 1024) as ActivityRep / 1024.0 * ACTIVITY_CONFLICT;
+            }
+        }
+        self.config = config;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{
+        literal::{Literal, LiteralSource},
+        solve::{test_formulas::solve_of, the_solve::literal_update},
+    };
+
+    #[test]
+    fn restarts_reuse_the_trail() {
+        let mut the_solve = solve_of("1 2 3 4 5 0\n");
+        the_solve.set_config(SolveConfig {
+            trail_reuse: true,
+            ..SolveConfig::default()
+        });
+        the_solve.activity = vec![5.0, 4.0, 2.0, 1.0, 3.0];
+
+        // choices of 1, 2, and 3 leave 5 as the next choice, which is preferred to 3
+        for v_id in 0..3 {
+            the_solve.add_fresh_level();
+            literal_update(
+                Literal::new(v_id, false),
+                LiteralSource::Choice,
+                &mut the_solve.levels,
+                &mut the_solve.decision_levels,
+                &mut the_solve.valuation,
+                &mut the_solve.trail,
+            )
+            .unwrap();
+        }
+        assert_eq!(the_solve.restart_level(), 2);
+
+        the_solve.config.trail_reuse = false;
+        assert_eq!(the_solve.restart_level(), 0);
+    }
+}
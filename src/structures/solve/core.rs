@@ -1,52 +1,94 @@
-use slotmap::SlotMap;
-
+use crate::errors::SolverError;
 use crate::structures::{
     clause::{
-        stored_clause::{ClauseSource, StoredClause},
+        clause_vec::ClauseVec,
+        stored_clause::{figure_out_intial_watches, ClauseSource, StoredClause, Watch},
         Clause,
     },
     formula::Formula,
     level::{Level, LevelIndex},
-    solve::{ClauseKey, Solve},
+    literal::LiteralSource,
+    solve::{
+        callbacks::StopState, clause_store::ClauseStore, config::SolveConfig, gauss::XorMatrix,
+        the_solve::literal_update, ClauseKey, Solve,
+    },
     valuation::{Valuation, ValuationVec},
+    variable::{Variable, VariableId},
 };
 
-use std::collections::VecDeque;
-
 impl Solve {
-    pub fn from_formula(formula: Formula) -> Solve {
+    pub fn from_formula(formula: Formula) -> Result<Solve, SolverError> {
         let variables = formula.variables;
         let clauses = formula.clauses;
+        let cardinality_constraints = formula.cardinality_constraints;
+        let xor_clauses = formula.xor_clauses;
 
         let mut the_solve = Solve {
             conflicts: 0,
             conflicts_since_last_forget: 0,
             conflicts_since_last_reset: 0,
             restarts: 0,
-            watch_q: VecDeque::with_capacity(variables.len() / 2),
-            valuation: Vec::<Option<bool>>::new_for_variables(variables.len()),
+            propagations: 0,
+            trail: Vec::with_capacity(variables.len()),
+            q_head: 0,
+            valuation: ValuationVec::new_for_variables(variables.len()),
+            saved_phases: None,
+            decision_levels: vec![None; variables.len()],
+            activity: vec![0.0; variables.len()],
+            watches: vec![Vec::new(); 2 * variables.len()],
+            binary_watches: vec![Vec::new(); 2 * variables.len()],
+            cardinality_watches: vec![Vec::new(); 2 * variables.len()],
             variables,
-            levels: vec![Level::new(0)],
-            formula_clauses: SlotMap::new(),
-            learnt_clauses: SlotMap::new(),
+            levels: vec![Level::new(0, 0)],
+            clause_store: ClauseStore::new(),
+            xors: XorMatrix::default(),
+            xor_reasons: vec![],
+            inconsistent: false,
+            prioritised: vec![],
+            assumptions: vec![],
+            failed_assumptions: vec![],
+            config: SolveConfig::default(),
+            interrupt: None,
+            exchange: None,
+            terminate: None,
+            learn: None,
+            stop: StopState::default(),
+            original_count: 0,
+            refutation: None,
+            origins_tracked: true,
         };
 
         for formula_clause in clauses {
-            match formula_clause.length() {
-                n if n < 2 => {
-                    panic!("c The formula contains a zero-or-one-length clause");
-                }
-                _ => {
-                    the_solve.store_clause(formula_clause.to_vec(), ClauseSource::Formula);
-                }
-            }
+            the_solve.add_clause(formula_clause, ClauseSource::Formula)?;
+        }
+        for (degree, literals) in cardinality_constraints {
+            the_solve.add_cardinality(literals, degree)?;
+        }
+        for (variables, parity) in xor_clauses {
+            the_solve.add_xor(variables, parity)?;
         }
 
-        the_solve
+        Ok(the_solve)
+    }
+
+    /// Adds a variable to the solve, which occurs in no clause
+    pub fn fresh_variable(&mut self, name: &str) -> VariableId {
+        let v_id = self.variables.len() as VariableId;
+        self.variables.push(Variable::new(name, v_id));
+        self.valuation.extend([None, None]);
+        if let Some(phases) = &mut self.saved_phases {
+            phases.extend([None, None]);
+        }
+        self.decision_levels.push(None);
+        self.activity.push(0.0);
+        self.watches.extend([vec![], vec![]]);
+        self.binary_watches.extend([vec![], vec![]]);
+        self.cardinality_watches.extend([vec![], vec![]]);
+        v_id
     }
 
     pub fn valuation_at(&self, level_index: LevelIndex) -> ValuationVec {
-        let mut valuation = ValuationVec::new_for_variables(self.valuation.len());
+        let mut valuation = ValuationVec::new_for_variables(self.variables.len());
         (0..=level_index).for_each(|i| {
             self.levels[i].literals().for_each(|l| {
                 let _ = valuation.update_value(l);
@@ -55,80 +97,385 @@ impl Solve {
         valuation
     }
 
-    pub fn stored_clauses(&self) -> impl Iterator<Item = &StoredClause> {
-        self.formula_clauses
-            .iter()
-            .chain(&self.learnt_clauses)
-            .map(|(_, sc)| sc)
+    pub fn stored_clauses(&self) -> impl Iterator<Item = StoredClause<'_>> {
+        self.clause_store.iter()
     }
 
     pub fn most_active_none(&self, val: &impl Valuation) -> Option<usize> {
+        // prioritised variables are chosen before any other variable
+        let prioritised = self
+            .prioritised
+            .iter()
+            .map(|&v_id| v_id as usize)
+            .filter(|&i| val.of_v_id(i as VariableId).is_none())
+            .max_by(|a, b| self.activity[*a].total_cmp(&self.activity[*b]));
+        if prioritised.is_some() {
+            return prioritised;
+        }
+
         val.values()
             .enumerate()
             .filter(|(_, v)| v.is_none())
-            .map(|(i, _)| (i, self.variables[i].activity()))
+            .map(|(i, _)| (i, self.activity[i]))
             .max_by(|a, b| a.1.total_cmp(&b.1))
             .map(|(a, _)| a)
     }
 
     /// Stores a clause with an automatically generated id.
-    /// Note: In order to use the clause the watch literals of the struct must be initialised.
-    pub fn store_clause(&mut self, clause: impl Clause, src: ClauseSource) -> ClauseKey {
+    /// The watches of the clause are initialised on the current valuation.
+    pub fn store_clause(
+        &mut self,
+        clause: impl Clause,
+        src: ClauseSource,
+    ) -> Result<ClauseKey, SolverError> {
         match clause.length() {
-            0 => panic!("Attempt to add an empty clause"),
-            _ => match &src {
-                ClauseSource::Formula => {
-                    let key = self.formula_clauses.insert_with_key(|k| {
-                        StoredClause::new_from(
-                            ClauseKey::Formula(k),
-                            clause.to_vec(),
-                            src,
-                            &self.valuation,
-                            &mut self.variables,
-                        )
-                    });
-
-                    ClauseKey::Formula(key)
-                }
-                ClauseSource::Resolution(_) => {
+            0 => Err(SolverError::EmptyClause),
+            _ => {
+                if let ClauseSource::Resolution(_) = src {
                     log::trace!("Learning clause {}", clause.as_string());
+                }
+
+                let the_wc = figure_out_intial_watches(clause.to_vec(), &self.valuation);
+                let key = self.clause_store.insert(&the_wc, src);
+
+                let current_a = self.clause_store.get_watched(key, Watch::A);
+                let current_b = self.clause_store.get_watched(key, Watch::B);
+
+                // binary clauses are kept as implications, and so their watches never move
+                if the_wc.len() == 2 {
+                    self.binary_watches[current_a.index()].push((key, current_b));
+                    self.binary_watches[current_b.index()].push((key, current_a));
+                } else {
+                    self.watches[current_a.index()].push((key, current_b));
+                    self.watches[current_b.index()].push((key, current_a));
+                }
+
+                Ok(key)
+            }
+        }
+    }
+
+    /// Adds a clause to the solve at level 0, backjumping to level 0 if needed.
+    ///
+    /// A unit clause is not stored, and instead the literal of the clause is set at level 0.
+    /// Otherwise, the clause is stored, and if the clause is unit on the valuation at level 0 the implied literal is set.
+    /// If every literal of the clause is false at level 0 the solve is noted to be inconsistent.
+    ///
+    /// A clause of the formula is given the next original index.
+    pub fn add_clause(&mut self, clause: ClauseVec, src: ClauseSource) -> Result<(), SolverError> {
+        let origins = match &src {
+            ClauseSource::Formula => vec![self.fresh_original_index()],
+            // clauses shared from other solves or refuting a cube are added without origins
+            ClauseSource::Resolution(origins) => {
+                if origins.is_empty() {
+                    self.origins_tracked = false;
+                }
+                origins.clone()
+            }
+        };
+        self.add_clause_with_origins(clause, src, origins)
+    }
+
+    /// Adds a clause to the solve at level 0, as [Solve::add_clause], with the given origins.
+    fn add_clause_with_origins(
+        &mut self,
+        mut clause: ClauseVec,
+        src: ClauseSource,
+        origins: Vec<u32>,
+    ) -> Result<(), SolverError> {
+        if self.current_level().index() > 0 {
+            self.backjump(0);
+        }
+        clause.sort_unstable();
+        clause.dedup();
 
-                    let key = self.learnt_clauses.insert_with_key(|k| {
-                        StoredClause::new_from(
-                            ClauseKey::Learnt(k),
-                            clause.to_vec(),
-                            src,
-                            &self.valuation,
-                            &mut self.variables,
-                        )
-                    });
-
-                    ClauseKey::Learnt(key)
+        match clause.len() {
+            0 => self.note_inconsistency(origins),
+            1 => match self.valuation.of_literal(clause[0]) {
+                Some(true) => {}
+                Some(false) => {
+                    let mut origins = origins;
+                    origins.extend(self.level_zero_origins(&clause));
+                    self.note_inconsistency(origins)
                 }
+                None => literal_update(
+                    clause[0],
+                    LiteralSource::Resolution(origins),
+                    &mut self.levels,
+                    &mut self.decision_levels,
+                    &mut self.valuation,
+                    &mut self.trail,
+                )?,
             },
+            _ => {
+                let key = self.store_clause(clause, src)?;
+                if let (ClauseKey::Formula(_), [index]) = (key, origins.as_slice()) {
+                    self.clause_store.set_original_index(key, *index);
+                }
+                // the initial watches are false only if no literal of the clause is true or unvalued
+                let watched_a = self.clause_store.get_watched(key, Watch::A);
+                let watched_b = self.clause_store.get_watched(key, Watch::B);
+                match (
+                    self.valuation.of_literal(watched_a),
+                    self.valuation.of_literal(watched_b),
+                ) {
+                    (Some(false), Some(false)) => {
+                        let literals = self.clause_store.retreive(key).clause_clone();
+                        let mut origins = origins;
+                        origins.extend(self.level_zero_origins(&literals));
+                        self.note_inconsistency(origins)
+                    }
+                    (None, Some(false)) | (Some(false), None) => {
+                        let implied = match self.valuation.of_literal(watched_a) {
+                            None => watched_a,
+                            _ => watched_b,
+                        };
+                        literal_update(
+                            implied,
+                            LiteralSource::StoredClause(key),
+                            &mut self.levels,
+                            &mut self.decision_levels,
+                            &mut self.valuation,
+                            &mut self.trail,
+                        )?
+                    }
+                    _ => {}
+                }
+            }
         }
+        Ok(())
     }
 
-    pub fn drop_learnt_clause(&mut self, clause_key: ClauseKey) {
-        if let ClauseKey::Learnt(key) = clause_key {
-            self.learnt_clauses.remove(key);
+    /// Adds the constraint that at least `degree` of the literals are true, at level 0.
+    ///
+    /// A constraint which is a clause is added as a clause, and a constraint which requires every literal which is not false at level 0 to be true is added as unit clauses.
+    /// Otherwise, the constraint is stored with the literals which are not false at level 0 watched.
+    ///
+    /// The constraint is given the next original index.
+    pub fn add_cardinality(
+        &mut self,
+        mut literals: ClauseVec,
+        mut degree: usize,
+    ) -> Result<(), SolverError> {
+        let original_index = self.fresh_original_index();
+        if self.current_level().index() > 0 {
+            self.backjump(0);
+        }
+        literals.sort_unstable();
+        literals.dedup();
+
+        // a literal and its negation are together always one true literal, and as literals are sorted the two are adjacent
+        let mut distinct = Vec::with_capacity(literals.len());
+        let mut index = 0;
+        while index < literals.len() {
+            if index + 1 < literals.len() && literals[index].v_id() == literals[index + 1].v_id() {
+                degree = degree.saturating_sub(1);
+                index += 2;
+            } else {
+                distinct.push(literals[index]);
+                index += 1;
+            }
+        }
+        let mut literals = distinct;
+
+        if degree == 0 {
+            return Ok(());
+        }
+
+        literals.sort_by_key(|literal| self.valuation.of_literal(*literal) == Some(false));
+        let open = literals
+            .iter()
+            .take_while(|literal| self.valuation.of_literal(**literal) != Some(false))
+            .count();
+
+        if open <= degree {
+            // each literal which is not false follows from the constraint together with the false literals
+            let mut origins = self.level_zero_origins(&literals[open..]);
+            origins.push(original_index);
+            if open < degree {
+                self.note_inconsistency(origins);
+            } else {
+                for literal in &literals[..open] {
+                    self.add_clause_with_origins(
+                        vec![*literal],
+                        ClauseSource::Resolution(origins.clone()),
+                        origins.clone(),
+                    )?;
+                }
+            }
+        } else if degree == 1 {
+            self.add_clause_with_origins(literals, ClauseSource::Formula, vec![original_index])?;
         } else {
-            panic!("hek")
+            let key = self.clause_store.insert_cardinality(&literals, degree);
+            self.clause_store.set_original_index(key, original_index);
+            for literal in &literals[..=degree] {
+                self.cardinality_watches[literal.index()].push(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the constraint that at most `bound` of the literals are true, at level 0.
+    pub fn add_at_most(
+        &mut self,
+        mut literals: ClauseVec,
+        bound: usize,
+    ) -> Result<(), SolverError> {
+        literals.sort_unstable();
+        literals.dedup();
+        let degree = literals.len().saturating_sub(bound);
+        let negated = literals.iter().map(|literal| literal.negate()).collect();
+        self.add_cardinality(negated, degree)
+    }
+
+    pub fn drop_learnt_clause(&mut self, clause_key: ClauseKey) -> Result<(), SolverError> {
+        match clause_key {
+            ClauseKey::Learnt(_) => {
+                self.clause_store.remove(clause_key);
+                Ok(())
+            }
+            ClauseKey::Formula(_) | ClauseKey::Cardinality(_) | ClauseKey::Xor(_) => {
+                Err(SolverError::ForgetFormulaClause(clause_key))
+            }
+        }
+    }
+
+    /// Compacts the clause store, and relocates every key held by the solve.
+    /// Any watch for a deleted clause is dropped.
+    /// If the reason for some literal was deleted, an error is returned before anything is compacted.
+    /// If the solve is stopped nothing is compacted, as propagation passes over deleted clauses.
+    pub fn collect_garbage(&mut self) -> Result<(), SolverError> {
+        if self.stop_requested() {
+            return Ok(());
+        }
+
+        let deleted_reason = self
+            .levels
+            .iter()
+            .flat_map(|level| level.observations())
+            .find_map(|(source, _)| match source {
+                LiteralSource::StoredClause(key)
+                    if !matches!(key, ClauseKey::Xor(_)) && self.clause_store.is_deleted(*key) =>
+                {
+                    Some(*key)
+                }
+                _ => None,
+            });
+        if let Some(key) = deleted_reason {
+            return Err(SolverError::ReasonDeleted(key));
         }
+
+        let relocation = self.clause_store.compact();
+
+        for watches in &mut self.cardinality_watches {
+            watches.retain_mut(|key| match relocation.relocate(*key) {
+                Some(relocated) => {
+                    *key = relocated;
+                    true
+                }
+                None => false,
+            });
+        }
+
+        for watches in self.watches.iter_mut().chain(&mut self.binary_watches) {
+            watches.retain_mut(|(key, _)| match relocation.relocate(*key) {
+                Some(relocated) => {
+                    *key = relocated;
+                    true
+                }
+                None => false,
+            });
+        }
+
+        for level in &mut self.levels {
+            for (source, _) in &mut level.observations {
+                // as no reason was deleted, each reason survived compaction
+                if let LiteralSource::StoredClause(key) = source {
+                    if let Some(relocated) = relocation.relocate(*key) {
+                        *key = relocated;
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
+    /// Removes each level above the given level, together with the literals of the levels.
+    ///
+    /// As a literal may be implied at a level below the current level, see [SolveConfig::chronological_backtracking](crate::structures::solve::config::SolveConfig::chronological_backtracking), literals of lower levels may follow the choice of a removed level on the trail.
+    /// These literals are kept, and are propagated again, as a clause watching such a literal may have been passed over due to a literal of a removed level.
     pub fn backjump(&mut self, to: LevelIndex) {
         log::trace!("Backjump from {} to {}", self.current_level().index(), to);
 
+        if self.current_level().index() <= to {
+            return;
+        }
+        // each literal of a removed level was added to the trail after the removed level above the given level
+        let start = self.levels[to + 1].trail_position();
+
         for _ in 0..(self.current_level().index() - to) {
             let the_level = self.levels.pop().unwrap();
             for literal in the_level.literals() {
                 log::trace!("Unset: {}", literal);
 
-                unsafe {
-                    let v_id = literal.v_id();
-                    *self.valuation.get_unchecked_mut(v_id) = None;
-                    self.variables.get_unchecked(v_id).clear_decision_level();
+                self.valuation.clear_value(literal.v_id());
+                self.decision_levels[literal.v_id() as usize] = None;
+            }
+        }
+
+        let mut kept = start;
+        for index in start..self.trail.len() {
+            let literal = self.trail[index];
+            if self.decision_levels[literal.v_id() as usize].is_some() {
+                self.trail[kept] = literal;
+                kept += 1;
+            }
+        }
+        self.trail.truncate(kept);
+
+        self.remove_xor_reasons_above(to);
+        // any literal before the first literal of a removed level has been propagated
+        self.q_head = std::cmp::min(self.q_head, start);
+    }
+
+    /// Removes each explanation from the XOR constraints noted at a level above the given level.
+    /// An explanation is noted at the level of the literal or conflict explained, and so any explanation of a kept literal is kept.
+    ///
+    /// As a literal may be implied at a level below the current level, explanations need not be noted in order of level.
+    /// If so, the explanations kept are moved down, and the key to each is relocated.
+    fn remove_xor_reasons_above(&mut self, to: LevelIndex) {
+        let Some(first) = self.xor_reasons.iter().position(|(level, _)| *level > to) else {
+            return;
+        };
+        if self.xor_reasons[first..]
+            .iter()
+            .all(|(level, _)| *level > to)
+        {
+            self.xor_reasons.truncate(first);
+            return;
+        }
+
+        let mut next = first as u32;
+        let relocation = self.xor_reasons[first..]
+            .iter()
+            .map(|(level, _)| {
+                (*level <= to).then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        self.xor_reasons.retain(|(level, _)| *level <= to);
+
+        for level in &mut self.levels {
+            for (source, _) in &mut level.observations {
+                if let LiteralSource::StoredClause(ClauseKey::Xor(index)) = source {
+                    if let Some(Some(relocated)) = (*index as usize)
+                        .checked_sub(first)
+                        .map(|offset| relocation[offset])
+                    {
+                        *index = relocated;
+                    }
                 }
             }
         }
@@ -142,3 +489,159 @@ impl std::fmt::Display for Solve {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{
+        literal::{Literal, LiteralSource},
+        solve::{test_formulas::solve_of, the_solve::literal_update_at, SolveResult},
+        valuation::Valuation,
+    };
+
+    #[test]
+    fn cardinality_constraints() {
+        // at least two of 1, 2, 3, 4, though not both 1 and 2
+        let mut the_solve = solve_of("p knf 4 2\nk 2 1 2 3 4 0\n-1 -2 0\n");
+        assert_eq!(the_solve.count_models(None).unwrap().to_string(), "7");
+
+        // at most one of 1, 2, 3, 4, with 1 or 2 and 3 or 4 required
+        let mut the_solve = solve_of("1 2 0\n3 4 0\n");
+        let literals = (0..4).map(|v_id| Literal::new(v_id, true)).collect();
+        the_solve.add_at_most(literals, 1).unwrap();
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+    }
+
+    #[test]
+    fn garbage_with_a_deleted_reason() {
+        let mut the_solve = solve_of("-1 0\n2 3 4 0\n");
+        the_solve
+            .add_clause(
+                vec![Literal::new(0, true), Literal::new(1, true)],
+                ClauseSource::Resolution(vec![]),
+            )
+            .unwrap();
+        let key = the_solve
+            .clause_store
+            .keys()
+            .find(|key| matches!(key, ClauseKey::Learnt(_)))
+            .unwrap();
+        assert_eq!(the_solve.decision_levels[1], Some(0));
+
+        // the learnt clause is the reason for 2, and so nothing is compacted
+        the_solve.drop_learnt_clause(key).unwrap();
+        let garbage = the_solve.clause_store.garbage();
+        assert!(matches!(
+            the_solve.collect_garbage(),
+            Err(SolverError::ReasonDeleted(deleted)) if deleted == key
+        ));
+        assert_eq!(the_solve.clause_store.garbage(), garbage);
+        assert_eq!(the_solve.clause_store.retreive(key).length(), 2);
+    }
+
+    #[test]
+    fn backjumps_keep_out_of_order_xor_reasons() {
+        let mut the_solve = solve_of("1 2 3 0\n");
+        let [one, two, three] = [0, 1, 2].map(|v_id| Literal::new(v_id, true));
+        for choice in [one, two] {
+            the_solve.add_fresh_level();
+            literal_update(
+                choice,
+                LiteralSource::Choice,
+                &mut the_solve.levels,
+                &mut the_solve.decision_levels,
+                &mut the_solve.valuation,
+                &mut the_solve.trail,
+            )
+            .unwrap();
+        }
+
+        // an explanation at level 2 is followed by an explanation of 3 at level 1
+        the_solve
+            .xor_reasons
+            .push((2, vec![one.negate(), two.negate()]));
+        the_solve.xor_reasons.push((1, vec![one.negate(), three]));
+        literal_update_at(
+            three,
+            LiteralSource::StoredClause(ClauseKey::Xor(1)),
+            1,
+            &mut the_solve.levels,
+            &mut the_solve.decision_levels,
+            &mut the_solve.valuation,
+            &mut the_solve.trail,
+        )
+        .unwrap();
+
+        // the explanation at level 2 is removed, and the key to the explanation of 3 is relocated
+        the_solve.backjump(1);
+        assert_eq!(the_solve.xor_reasons, vec![(1, vec![one.negate(), three])]);
+        assert!(matches!(
+            the_solve.levels[1].observations[..],
+            [(LiteralSource::StoredClause(ClauseKey::Xor(0)), literal)] if literal == three
+        ));
+
+        the_solve.backjump(0);
+        assert!(the_solve.xor_reasons.is_empty());
+    }
+
+    #[test]
+    fn backjumps_keep_out_of_order_literals() {
+        let mut the_solve = solve_of("-1 2 3 0\n-3 4 5 0\n");
+        let reason = the_solve.clause_store.keys().next().unwrap();
+        let [one, two, three] = [0, 1, 2].map(|v_id| Literal::new(v_id, true));
+
+        for choice in [one, two] {
+            the_solve.add_fresh_level();
+            literal_update(
+                choice,
+                LiteralSource::Choice,
+                &mut the_solve.levels,
+                &mut the_solve.decision_levels,
+                &mut the_solve.valuation,
+                &mut the_solve.trail,
+            )
+            .unwrap();
+            assert!(the_solve.propagate().unwrap().is_none());
+        }
+
+        // 3 is implied at level 1, and so follows the choice of level 2 on the trail
+        literal_update_at(
+            three,
+            LiteralSource::StoredClause(reason),
+            1,
+            &mut the_solve.levels,
+            &mut the_solve.decision_levels,
+            &mut the_solve.valuation,
+            &mut the_solve.trail,
+        )
+        .unwrap();
+        assert!(the_solve.propagate().unwrap().is_none());
+        assert_eq!(the_solve.q_head, 3);
+
+        // 3 is kept, and propagated again from the position of the removed choice
+        the_solve.backjump(1);
+        assert_eq!(the_solve.trail, vec![one, three]);
+        assert_eq!(the_solve.decision_levels[2], Some(1));
+        assert_eq!(the_solve.valuation.of_v_id(1), None);
+        assert_eq!(the_solve.q_head, 1);
+        assert!(the_solve.propagate().unwrap().is_none());
+        assert_eq!(the_solve.q_head, 2);
+
+        // the clause watching 3 is still watched
+        the_solve.add_fresh_level();
+        literal_update(
+            Literal::new(3, false),
+            LiteralSource::Choice,
+            &mut the_solve.levels,
+            &mut the_solve.decision_levels,
+            &mut the_solve.valuation,
+            &mut the_solve.trail,
+        )
+        .unwrap();
+        assert!(the_solve.propagate().unwrap().is_none());
+        assert_eq!(the_solve.valuation.of_v_id(4), Some(true));
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::Clause,
+        literal::{Literal, LiteralSource},
+        model_count::ModelCount,
+        solve::{the_solve::literal_update, ClauseKey, Solve},
+        valuation::Valuation,
+        variable::VariableId,
+    },
+};
+
+/// The greatest count of words of the keys of the cache of counts, past which the cache is cleared
+const CACHE_WORDS: usize = 1 << 24;
+
+struct Component {
+    variables: Vec<VariableId>,
+    clauses: Vec<ClauseKey>,
+    /// The XOR constraints of the component, by index
+    xors: Vec<usize>,
+    /// For each variable of the component, the count of clauses of the component in which the variable occurs
+    scores: Vec<usize>,
+}
+
+struct Counter {
+    /// For each variable, the formula clauses in which the variable occurs
+    occurrences: Vec<Vec<ClauseKey>>,
+    /// For each variable, the XOR constraints in which the variable occurs
+    xor_occurrences: Vec<Vec<usize>>,
+    /// For each variable, whether the variable is part of the projection
+    projected: Vec<bool>,
+    /// For each variable, the most recent search for components to reach the variable
+    marks: Vec<usize>,
+    search: usize,
+    /// For each variable, the count of clauses of the component being found in which the variable occurs
+    scores: Vec<usize>,
+    cache: HashMap<Vec<u32>, ModelCount>,
+    /// The count of words of the keys of the cache
+    cache_words: usize,
+}
+
+impl Counter {
+    /// Caches the count of a component, with the cache cleared first if the keys of the cache would exceed [CACHE_WORDS].
+    fn insert(&mut self, key: Vec<u32>, count: ModelCount) {
+        if self.cache_words + key.len() > CACHE_WORDS {
+            self.cache.clear();
+            self.cache_words = 0;
+        }
+        self.cache_words += key.len();
+        self.cache.insert(key, count);
+    }
+}
+
+/// A step of the search for a count
+enum Task {
+    /// The product of the count of each component, with the product of the components already counted
+    Product {
+        components: Vec<Component>,
+        count: ModelCount,
+    },
+    /// The sum of the count of the component under each polarity of the choice, with the sum of the polarities already tried
+    Branch {
+        component: Component,
+        cache_key: Vec<u32>,
+        choice: VariableId,
+        polarities: std::array::IntoIter<bool, 2>,
+        count: ModelCount,
+    },
+}
+
+impl Solve {
+    /// The count of models of the formula, projected onto the given variables, or over every variable if no projection is given.
+    ///
+    /// The count is by DPLL with component decomposition and a cache of the count of each component, in the spirit of sharpSAT, with variables of the projection chosen first.
+    /// Only variables of the solve are counted, and any clause learnt by the solve should be a clause of the formula.
+    /// The search learns no clauses, and so a count may take exponentially many conflicts where a solve would learn to avoid these.
+    pub fn count_models(
+        &mut self,
+        projection: Option<Vec<VariableId>>,
+    ) -> Result<ModelCount, SolverError> {
+        if self.current_level().index() > 0 {
+            self.backjump(0);
+        }
+        if !self.inconsistent {
+            if let Some(conflict) = self.propagate()? {
+                self.refute(conflict);
+            }
+        }
+        if self.inconsistent {
+            return Ok(ModelCount::zero());
+        }
+
+        let variable_count = self.variables.len();
+        let projected = match projection {
+            Some(variables) => {
+                let mut projected = vec![false; variable_count];
+                for v_id in variables {
+                    projected[v_id as usize] = true;
+                }
+                projected
+            }
+            None => vec![true; variable_count],
+        };
+
+        let mut occurrences = vec![vec![]; variable_count];
+        for key in self.clause_store.keys() {
+            if let ClauseKey::Formula(_) | ClauseKey::Cardinality(_) = key {
+                for v_id in self.clause_store.retreive(key).variables() {
+                    occurrences[v_id as usize].push(key);
+                }
+            }
+        }
+
+        let mut xor_occurrences = vec![vec![]; variable_count];
+        for (index, (variables, _)) in self.xors.constraints.iter().enumerate() {
+            for &v_id in variables {
+                xor_occurrences[v_id as usize].push(index);
+            }
+        }
+
+        let mut counter = Counter {
+            occurrences,
+            xor_occurrences,
+            projected,
+            marks: vec![0; variable_count],
+            search: 0,
+            scores: vec![0; variable_count],
+            cache: HashMap::new(),
+            cache_words: 0,
+        };
+
+        let unvalued = (0..variable_count as VariableId)
+            .filter(|&v_id| self.valuation.of_v_id(v_id).is_none())
+            .collect::<Vec<_>>();
+        self.count_variables(&unvalued, &mut counter)
+    }
+
+    /// The count of the unvalued variables, as the product of the count of each component of the variables.
+    ///
+    /// The search is over an explicit stack of tasks, and the count of each task is passed to the task below once found.
+    fn count_variables(
+        &mut self,
+        variables: &[VariableId],
+        counter: &mut Counter,
+    ) -> Result<ModelCount, SolverError> {
+        let mut stack = vec![self.product_task(variables, counter)];
+        let mut found: Option<ModelCount> = None;
+
+        while let Some(task) = stack.pop() {
+            match task {
+                Task::Product {
+                    mut components,
+                    mut count,
+                } => {
+                    if let Some(component_count) = found.take() {
+                        count = count.mul(&component_count);
+                    }
+                    let next = match count.is_zero() {
+                        true => None,
+                        false => components.pop(),
+                    };
+                    match next {
+                        None => found = Some(count),
+                        Some(component) => {
+                            stack.push(Task::Product { components, count });
+                            match self.component_task(component, counter) {
+                                Ok(task) => stack.push(task),
+                                Err(count) => found = Some(count),
+                            }
+                        }
+                    }
+                }
+
+                Task::Branch {
+                    component,
+                    cache_key,
+                    choice,
+                    mut polarities,
+                    mut count,
+                } => {
+                    if let Some(branch_count) = found.take() {
+                        self.backjump(self.current_level().index() - 1);
+                        count = count.add(&branch_count);
+                    }
+                    // without a projected choice, the component is only checked to be satisfiable
+                    let existential = !counter.projected[choice as usize];
+                    let satisfied = existential && !count.is_zero();
+                    let polarity = match polarities.next() {
+                        Some(polarity) if !satisfied => polarity,
+                        _ => {
+                            counter.insert(cache_key, count.clone());
+                            found = Some(count);
+                            continue;
+                        }
+                    };
+
+                    self.add_fresh_level();
+                    literal_update(
+                        Literal::new(choice, polarity),
+                        LiteralSource::Choice,
+                        &mut self.levels,
+                        &mut self.decision_levels,
+                        &mut self.valuation,
+                        &mut self.trail,
+                    )?;
+                    let conflict = self.propagate()?;
+                    let unvalued = component
+                        .variables
+                        .iter()
+                        .filter(|&&v_id| self.valuation.of_v_id(v_id).is_none())
+                        .copied()
+                        .collect::<Vec<_>>();
+                    stack.push(Task::Branch {
+                        component,
+                        cache_key,
+                        choice,
+                        polarities,
+                        count,
+                    });
+                    match conflict {
+                        Some(_) => found = Some(ModelCount::zero()),
+                        None => stack.push(self.product_task(&unvalued, counter)),
+                    }
+                }
+            }
+        }
+        Ok(found.unwrap_or_else(ModelCount::one))
+    }
+
+    /// The task of counting the unvalued variables, by the components of the variables.
+    fn product_task(&self, variables: &[VariableId], counter: &mut Counter) -> Task {
+        let (components, free_projected) = self.components(variables, counter);
+        let mut count = ModelCount::one();
+        count.double(free_projected);
+        Task::Product { components, count }
+    }
+
+    /// The task of counting the component by a choice, or the count of the component if cached or without a choice.
+    ///
+    /// A component is cached by its variables and clauses, with the count of true literals of each cardinality constraint and the remaining parity of each XOR constraint.
+    /// A component without a projected variable counts one if satisfiable, and so its search stops at the first model.
+    fn component_task(&self, component: Component, counter: &Counter) -> Result<Task, ModelCount> {
+        let mut cache_key = component.variables.clone();
+        cache_key.sort_unstable();
+        cache_key.push(u32::MAX);
+        let mut clause_offsets = component
+            .clauses
+            .iter()
+            .map(|key| key.offset())
+            .collect::<Vec<_>>();
+        clause_offsets.sort_unstable();
+        cache_key.extend(clause_offsets);
+        // the formula which remains for a cardinality constraint also depends on the count of literals which are true
+        for &key in &component.clauses {
+            if let ClauseKey::Cardinality(_) = key {
+                cache_key.push(self.true_count(key, usize::MAX) as u32);
+            }
+        }
+        cache_key.push(u32::MAX);
+        let mut xors = component.xors.clone();
+        xors.sort_unstable();
+        for index in xors {
+            cache_key.push(index as u32);
+            cache_key.push(self.residual_parity(index) as u32);
+        }
+
+        if let Some(count) = counter.cache.get(&cache_key) {
+            return Err(count.clone());
+        }
+
+        // the variable which occurs most often in the clauses of the component, with priority to the projection
+        let choice = component
+            .variables
+            .iter()
+            .zip(&component.scores)
+            .max_by_key(|(v_id, score)| (counter.projected[**v_id as usize], **score))
+            .map(|(v_id, _)| *v_id);
+        match choice {
+            Some(choice) => Ok(Task::Branch {
+                component,
+                cache_key,
+                choice,
+                polarities: [false, true].into_iter(),
+                count: ModelCount::zero(),
+            }),
+            None => Err(ModelCount::one()),
+        }
+    }
+
+    /// The components of the unvalued variables, and the count of projected variables which are in no component.
+    /// Two variables are of the same component if both occur in some clause or constraint which is not satisfied.
+    fn components(
+        &self,
+        variables: &[VariableId],
+        counter: &mut Counter,
+    ) -> (Vec<Component>, usize) {
+        counter.search += 1;
+        let search = counter.search;
+
+        let mut components = vec![];
+        let mut free_projected = 0;
+        let mut seen_clauses = HashSet::new();
+        let mut seen_xors = HashSet::new();
+
+        for &v_id in variables {
+            if counter.marks[v_id as usize] == search {
+                continue;
+            }
+            counter.marks[v_id as usize] = search;
+
+            let mut component = Component {
+                variables: vec![],
+                clauses: vec![],
+                xors: vec![],
+                scores: vec![],
+            };
+            let mut queue = vec![v_id];
+            while let Some(variable) = queue.pop() {
+                component.variables.push(variable);
+                for &key in &counter.occurrences[variable as usize] {
+                    if !seen_clauses.insert(key) {
+                        continue;
+                    }
+                    if self.is_satisfied(key) {
+                        continue;
+                    }
+                    let clause = self.clause_store.retreive(key);
+                    component.clauses.push(key);
+                    for other in clause.variables() {
+                        if self.valuation.of_v_id(other).is_none() {
+                            counter.scores[other as usize] += 1;
+                            if counter.marks[other as usize] != search {
+                                counter.marks[other as usize] = search;
+                                queue.push(other);
+                            }
+                        }
+                    }
+                }
+                for &index in &counter.xor_occurrences[variable as usize] {
+                    if !seen_xors.insert(index) {
+                        continue;
+                    }
+                    let unvalued = self.xors.constraints[index]
+                        .0
+                        .iter()
+                        .filter(|&&other| self.valuation.of_v_id(other).is_none())
+                        .copied()
+                        .collect::<Vec<_>>();
+                    if unvalued.is_empty() {
+                        continue;
+                    }
+                    component.xors.push(index);
+                    for other in unvalued {
+                        counter.scores[other as usize] += 1;
+                        if counter.marks[other as usize] != search {
+                            counter.marks[other as usize] = search;
+                            queue.push(other);
+                        }
+                    }
+                }
+            }
+
+            for variable in &component.variables {
+                component
+                    .scores
+                    .push(std::mem::take(&mut counter.scores[*variable as usize]));
+            }
+
+            if component.clauses.is_empty() && component.xors.is_empty() {
+                if counter.projected[v_id as usize] {
+                    free_projected += 1;
+                }
+            } else {
+                components.push(component);
+            }
+        }
+
+        (components, free_projected)
+    }
+
+    /// Whether the clause has some true literal, or the cardinality constraint has enough true literals
+    fn is_satisfied(&self, key: ClauseKey) -> bool {
+        let required = match key {
+            ClauseKey::Cardinality(_) => self.clause_store.degree(key),
+            _ => 1,
+        };
+        self.true_count(key, required) == required
+    }
+
+    /// The parity the unvalued variables of the XOR constraint must have, given the variables which are true
+    fn residual_parity(&self, index: usize) -> bool {
+        let (variables, parity) = &self.xors.constraints[index];
+        let true_count = variables
+            .iter()
+            .filter(|&&v_id| self.valuation.of_v_id(v_id) == Some(true))
+            .count();
+        *parity ^ (true_count % 2 == 1)
+    }
+
+    /// The count of true literals of the clause or cardinality constraint, up to the limit
+    fn true_count(&self, key: ClauseKey, limit: usize) -> usize {
+        self.clause_store
+            .retreive(key)
+            .literals()
+            .filter(|literal| self.valuation.of_literal(*literal) == Some(true))
+            .take(limit)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{formula::Formula, solve::test_formulas::solve_of};
+
+    #[test]
+    fn count_with_components() {
+        // two copies of (1 or 2) and (-1 or -2 or 3), on distinct variables, with 5 * 5 models
+        let formula =
+            Formula::from_dimacs("1 2 0\n-1 -2 3 0\n4 5 0\n-4 -5 6 0\nc p show 1 2 4 0\n").unwrap();
+        let projection = formula.projection.clone();
+
+        let mut the_solve = Solve::from_formula(formula).unwrap();
+        assert_eq!(the_solve.count_models(None).unwrap().to_string(), "25");
+        assert_eq!(the_solve.count_models(projection).unwrap().to_string(), "6");
+    }
+
+    #[test]
+    fn count_a_long_clause() {
+        // each choice on the clause leaves a single component, so the search is as deep as the clause is long
+        let length = 1000;
+        let clause = (1..=length).map(|v| format!("{v} ")).collect::<String>();
+        let mut the_solve = solve_of(&format!("{clause}0\n"));
+        let mut expected = ModelCount::one();
+        expected.double(length);
+        assert_eq!(
+            the_solve
+                .count_models(None)
+                .unwrap()
+                .add(&ModelCount::one()),
+            expected
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::{clause_vec::ClauseVec, stored_clause::ClauseSource, Clause},
+        literal::{Literal, LiteralSource},
+        solve::{the_solve::literal_update, Solve, SolveResult},
+        valuation::Valuation,
+        variable::VariableId,
+    },
+};
+
+/// The count of variables examined by lookahead for each split
+const LOOKAHEAD_CANDIDATES: usize = 32;
+
+enum Lookahead {
+    /// Every variable is valued
+    Complete,
+    /// Both values of some variable lead to a conflict
+    Refuted,
+    /// Some value of a variable leads to a conflict, and so the literal is implied
+    Forced(Literal),
+    Split(VariableId),
+}
+
+impl Solve {
+    /// Splits the formula into cubes of at most the given count of literals, by lookahead.
+    ///
+    /// Refuted cubes are dropped, so the cubes together with the refuted cubes cover every assignment.
+    /// If every cube is refuted there are no cubes, and the formula is unsatisfiable.
+    pub fn cubes(&mut self, depth: usize) -> Result<Vec<ClauseVec>, SolverError> {
+        if self.current_level().index() > 0 {
+            self.backjump(0);
+        }
+        if !self.inconsistent {
+            if let Some(conflict) = self.propagate()? {
+                self.refute(conflict);
+            }
+        }
+        if self.inconsistent {
+            return Ok(vec![]);
+        }
+
+        let mut occurrences = vec![0; self.variables.len()];
+        for clause in self.stored_clauses() {
+            for v_id in clause.variables() {
+                occurrences[v_id as usize] += 1;
+            }
+        }
+
+        let mut cubes = vec![];
+        self.split(depth, &occurrences, &mut vec![], &mut cubes)?;
+        Ok(cubes)
+    }
+
+    fn split(
+        &mut self,
+        depth: usize,
+        occurrences: &[usize],
+        cube: &mut ClauseVec,
+        cubes: &mut Vec<ClauseVec>,
+    ) -> Result<(), SolverError> {
+        if depth == 0 {
+            cubes.push(cube.clone());
+            return Ok(());
+        }
+
+        match self.lookahead(occurrences)? {
+            Lookahead::Complete => cubes.push(cube.clone()),
+            Lookahead::Refuted => {}
+            Lookahead::Forced(literal) => {
+                if self.try_literal(literal)?.is_some() {
+                    self.split(depth, occurrences, cube, cubes)?;
+                }
+                self.backjump(self.current_level().index() - 1);
+            }
+            Lookahead::Split(v_id) => {
+                for polarity in [true, false] {
+                    let literal = Literal::new(v_id, polarity);
+                    if self.try_literal(literal)?.is_some() {
+                        cube.push(literal);
+                        self.split(depth - 1, occurrences, cube, cubes)?;
+                        cube.pop();
+                    }
+                    self.backjump(self.current_level().index() - 1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets each value of the most frequent variables in turn, choosing the variable with the greatest product of the counts of literals propagated.
+    fn lookahead(&mut self, occurrences: &[usize]) -> Result<Lookahead, SolverError> {
+        let mut candidates = (0..self.variables.len() as VariableId)
+            .filter(|&v_id| self.valuation.of_v_id(v_id).is_none())
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Ok(Lookahead::Complete);
+        }
+        candidates.sort_by_key(|&v_id| std::cmp::Reverse(occurrences[v_id as usize]));
+        candidates.truncate(LOOKAHEAD_CANDIDATES);
+
+        let mut best = (0, candidates[0]);
+        for v_id in candidates {
+            let mut counts = [0; 2];
+            for (count, polarity) in counts.iter_mut().zip([true, false]) {
+                let outcome = self.try_literal(Literal::new(v_id, polarity))?;
+                self.backjump(self.current_level().index() - 1);
+                match outcome {
+                    Some(valued) => *count = valued,
+                    None => {
+                        let other = Literal::new(v_id, !polarity);
+                        let other_outcome = self.try_literal(other)?;
+                        self.backjump(self.current_level().index() - 1);
+                        return match other_outcome {
+                            Some(_) => Ok(Lookahead::Forced(other)),
+                            None => Ok(Lookahead::Refuted),
+                        };
+                    }
+                }
+            }
+            let score = (counts[0] + 1) * (counts[1] + 1);
+            if score > best.0 {
+                best = (score, v_id);
+            }
+        }
+        Ok(Lookahead::Split(best.1))
+    }
+
+    /// Sets the literal on a fresh level and propagates, returning the count of literals valued if no conflict is found.
+    /// The level is kept, and so should be undone by a backjump.
+    fn try_literal(&mut self, literal: Literal) -> Result<Option<usize>, SolverError> {
+        let before = self.trail.len();
+        self.add_fresh_level();
+        literal_update(
+            literal,
+            LiteralSource::Choice,
+            &mut self.levels,
+            &mut self.decision_levels,
+            &mut self.valuation,
+            &mut self.trail,
+        )?;
+        match self.propagate()? {
+            Some(_) => Ok(None),
+            None => Ok(Some(self.trail.len() - before)),
+        }
+    }
+
+    /// Solves the formula under each cube in turn, returning the result together with the index of the cube which was satisfiable, if some cube was.
+    ///
+    /// If the cubes cover every assignment, as for cubes from [Solve::cubes], the formula is unsatisfiable if each cube is.
+    /// Clauses learnt under one cube are kept for each later cube, together with the negation of the assumptions which refute a cube.
+    pub fn solve_cubes(
+        &mut self,
+        cubes: &[ClauseVec],
+    ) -> Result<(SolveResult, Option<usize>), SolverError> {
+        let mut unknown = false;
+        for (index, cube) in cubes.iter().enumerate() {
+            match self.solve_under_assumptions(cube)?.0 {
+                SolveResult::Satisfiable => return Ok((SolveResult::Satisfiable, Some(index))),
+                SolveResult::Unknown => unknown = true,
+                SolveResult::Unsatisfiable => {
+                    if self.inconsistent {
+                        return Ok((SolveResult::Unsatisfiable, None));
+                    }
+                    let refutation = self
+                        .failed_assumptions()
+                        .iter()
+                        .map(|literal| literal.negate())
+                        .collect();
+                    self.add_clause(refutation, ClauseSource::Resolution(vec![]))?;
+                }
+            }
+        }
+        match unknown {
+            true => Ok((SolveResult::Unknown, None)),
+            false => Ok((SolveResult::Unsatisfiable, None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::solve::test_formulas::{pigeonhole, solve_of};
+
+    #[test]
+    fn cube_and_conquer() {
+        // the pigeonhole principle for three pigeons and two holes
+        let mut the_solve = Solve::from_formula(pigeonhole(2)).unwrap();
+        let cubes = the_solve.cubes(2).unwrap();
+        assert!(matches!(
+            the_solve.solve_cubes(&cubes).unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+
+        // a satisfiable formula, split on one of 1, 2, 3 and then one of 4, 5
+        let mut the_solve = solve_of("1 2 0\n-1 3 0\n-2 -3 0\n4 5 0\n");
+        let cubes = the_solve.cubes(2).unwrap();
+        assert_eq!(cubes.len(), 4);
+        assert!(matches!(
+            the_solve.solve_cubes(&cubes).unwrap().0,
+            SolveResult::Satisfiable
+        ));
+    }
+}
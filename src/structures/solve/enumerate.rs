@@ -0,0 +1,206 @@
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::{clause_vec::ClauseVec, stored_clause::ClauseSource},
+        literal::Literal,
+        solve::{Solve, SolveResult},
+        valuation::Valuation,
+        variable::VariableId,
+    },
+};
+
+/// An iterator over the models of a solve, with each model restricted to the variables of the projection.
+///
+/// Each model is blocked by a clause added at level 0, so clauses learnt for previous models are kept.
+/// A blocking clause is not a clause of the formula, and so is added without an original index, and once a model is blocked the solve has no core.
+pub struct Models<'solve> {
+    solve: &'solve mut Solve,
+    projection: Vec<VariableId>,
+    decision_blocking: bool,
+    exhausted: bool,
+    stopped: bool,
+    /// The variables chosen first before the enumeration, if replaced by the projection
+    previous_prioritised: Option<Vec<VariableId>>,
+}
+
+impl Solve {
+    /// The models of the solve over the given projection, or over every variable if no projection is given.
+    /// If `decision_blocking` is set, each model is blocked by the negation of the choices made for the model, with the variables of the projection chosen first.
+    pub fn models(
+        &mut self,
+        projection: Option<Vec<VariableId>>,
+        decision_blocking: bool,
+    ) -> Models<'_> {
+        let mut previous_prioritised = None;
+        let projection = match projection {
+            Some(variables) => {
+                if decision_blocking {
+                    previous_prioritised =
+                        Some(std::mem::replace(&mut self.prioritised, variables.clone()));
+                }
+                variables
+            }
+            None => (0..self.variables.len() as VariableId).collect(),
+        };
+
+        Models {
+            solve: self,
+            projection,
+            decision_blocking,
+            exhausted: false,
+            stopped: false,
+            previous_prioritised,
+        }
+    }
+}
+
+impl Models<'_> {
+    /// Whether every model of the solve has been found
+    pub fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    fn restore_prioritised(&mut self) {
+        if let Some(prioritised) = self.previous_prioritised.take() {
+            self.solve.prioritised = prioritised;
+        }
+    }
+
+    fn model(&self) -> Vec<Literal> {
+        self.projection
+            .iter()
+            .filter_map(|&v_id| {
+                self.solve
+                    .valuation
+                    .of_v_id(v_id)
+                    .map(|polarity| Literal::new(v_id, polarity))
+            })
+            .collect()
+    }
+
+    fn blocking_clause(&self, model: &[Literal]) -> ClauseVec {
+        if self.decision_blocking {
+            self.solve
+                .levels
+                .iter()
+                .filter_map(|level| level.choice)
+                .filter(|choice| self.projection.contains(&choice.v_id()))
+                .map(|choice| choice.negate())
+                .collect()
+        } else {
+            model.iter().map(|literal| literal.negate()).collect()
+        }
+    }
+}
+
+impl Iterator for Models<'_> {
+    type Item = Result<Vec<Literal>, SolverError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted || self.stopped {
+            return None;
+        }
+
+        match self.solve.do_solve() {
+            Ok((SolveResult::Satisfiable, _)) => {
+                let model = self.model();
+                let blocking_clause = self.blocking_clause(&model);
+                match self
+                    .solve
+                    .add_clause(blocking_clause, ClauseSource::Resolution(vec![]))
+                {
+                    Ok(()) => Some(Ok(model)),
+                    Err(e) => {
+                        self.stopped = true;
+                        Some(Err(e))
+                    }
+                }
+            }
+            Ok((SolveResult::Unsatisfiable, _)) => {
+                self.exhausted = true;
+                self.restore_prioritised();
+                None
+            }
+            Ok((SolveResult::Unknown, _)) => {
+                self.stopped = true;
+                self.restore_prioritised();
+                None
+            }
+            Err(e) => {
+                self.stopped = true;
+                self.restore_prioritised();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Drop for Models<'_> {
+    fn drop(&mut self) {
+        self.restore_prioritised();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::formula::Formula;
+
+    #[test]
+    fn enumerate_models() {
+        // (1 or 2) and (-1 or -2 or 3) has five models, and three models over 1 and 2
+        let formula = || Formula::from_dimacs("1 2 0\n-1 -2 3 0\n").unwrap();
+
+        let mut the_solve = Solve::from_formula(formula()).unwrap();
+        let mut models = the_solve.models(None, false);
+        let all_models = models.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(all_models.len(), 5);
+        assert!(models.exhausted());
+
+        for decision_blocking in [false, true] {
+            let mut the_solve = Solve::from_formula(formula()).unwrap();
+            let mut projected = the_solve
+                .models(Some(vec![0, 1]), decision_blocking)
+                .map(|m| m.unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(projected.len(), 3);
+            projected.sort();
+            projected.dedup();
+            assert_eq!(projected.len(), 3);
+        }
+
+        // the variables chosen first are restored once exhausted, and when dropped
+        let mut the_solve = Solve::from_formula(formula()).unwrap();
+        the_solve.prioritised = vec![2];
+        let mut models = the_solve.models(Some(vec![0, 1]), true);
+        assert_eq!(models.solve.prioritised, vec![0, 1]);
+        assert_eq!(models.by_ref().count(), 3);
+        assert_eq!(models.solve.prioritised, vec![2]);
+        drop(models);
+
+        let mut the_solve = Solve::from_formula(formula()).unwrap();
+        the_solve.prioritised = vec![2];
+        let mut models = the_solve.models(Some(vec![0]), true);
+        assert!(models.next().is_some());
+        drop(models);
+        assert_eq!(the_solve.prioritised, vec![2]);
+    }
+
+    #[test]
+    fn blocking_clauses_are_not_original() {
+        let mut the_solve =
+            Solve::from_formula(Formula::from_dimacs("1 2 0\n-1 -2 3 0\n").unwrap()).unwrap();
+        assert_eq!(the_solve.models(None, false).count(), 5);
+        assert_eq!(the_solve.original_count, 2);
+        assert_eq!(the_solve.unsat_core(), None);
+
+        // a clause of the formula added after the enumeration has the next original index
+        the_solve
+            .add_clause(
+                vec![Literal::new(0, true), Literal::new(1, false)],
+                ClauseSource::Formula,
+            )
+            .unwrap();
+        assert_eq!(the_solve.original_count, 3);
+    }
+}
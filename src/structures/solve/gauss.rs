@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::clause_vec::ClauseVec,
+        level::LevelIndex,
+        literal::{Literal, LiteralSource},
+        solve::{the_solve::literal_update_at, ClauseKey, Solve},
+        valuation::Valuation,
+        variable::VariableId,
+    },
+};
+
+/// The XOR constraints as the rows of a matrix over GF(2) in reduced row echelon form, with a column for each variable of some constraint.
+///
+/// Pivots are not undone on a backjump, as any pivot is as good as any other.
+#[derive(Default)]
+pub struct XorMatrix {
+    /// The variable of each column
+    columns: Vec<VariableId>,
+    column_of: HashMap<VariableId, usize>,
+    /// The columns of each row, as a bitset
+    rows: Vec<Vec<u64>>,
+    /// For each row, whether an odd count of the variables of the row are true
+    parities: Vec<bool>,
+    /// For each row, the pivot column of the row, if some column of the row is unvalued
+    pivots: Vec<Option<usize>>,
+    /// Each XOR constraint as added, with duplicate variables cancelled
+    pub constraints: Vec<(Vec<VariableId>, bool)>,
+}
+
+impl XorMatrix {
+    /// The variables of the XOR constraints
+    pub fn variables(&self) -> &[VariableId] {
+        &self.columns
+    }
+
+    fn column(&mut self, v_id: VariableId) -> usize {
+        if let Some(&column) = self.column_of.get(&v_id) {
+            return column;
+        }
+        let column = self.columns.len();
+        self.columns.push(v_id);
+        self.column_of.insert(v_id, column);
+        let words = self.columns.len().div_ceil(64);
+        for row in &mut self.rows {
+            row.resize(words, 0);
+        }
+        column
+    }
+
+    fn eliminate(&mut self, column: usize, pivot_row: usize) {
+        for index in 0..self.rows.len() {
+            if index != pivot_row && has_column(&self.rows[index], column) {
+                let (pivot, row) = pair_mut(&mut self.rows, pivot_row, index);
+                for (word, pivot_word) in row.iter_mut().zip(pivot.iter()) {
+                    *word ^= pivot_word;
+                }
+                self.parities[index] ^= self.parities[pivot_row];
+            }
+        }
+    }
+}
+
+impl Solve {
+    /// Adds the constraint that an odd count of the variables are true if the parity is true, and an even count otherwise, at level 0.
+    pub fn add_xor(
+        &mut self,
+        mut variables: Vec<VariableId>,
+        mut parity: bool,
+    ) -> Result<(), SolverError> {
+        if self.current_level().index() > 0 {
+            self.backjump(0);
+        }
+
+        // a variable added to itself is zero, and as variables are sorted duplicates are adjacent
+        variables.sort_unstable();
+        let mut distinct: Vec<VariableId> = Vec::with_capacity(variables.len());
+        for v_id in variables {
+            if distinct.last() == Some(&v_id) {
+                distinct.pop();
+            } else {
+                distinct.push(v_id);
+            }
+        }
+        self.xors.constraints.push((distinct.clone(), parity));
+
+        let columns = distinct
+            .iter()
+            .map(|&v_id| self.xors.column(v_id))
+            .collect::<Vec<_>>();
+        let mut row = vec![0_u64; self.xors.columns.len().div_ceil(64)];
+        for column in columns {
+            row[column / 64] |= 1 << (column % 64);
+        }
+
+        // the row is reduced by each row whose pivot is a column of the row
+        for (other, pivot) in self.xors.pivots.iter().enumerate() {
+            if let Some(pivot) = pivot {
+                if has_column(&row, *pivot) {
+                    for (word, other_word) in row.iter_mut().zip(&self.xors.rows[other]) {
+                        *word ^= other_word;
+                    }
+                    parity ^= self.xors.parities[other];
+                }
+            }
+        }
+
+        let pivot = set_columns(&row).next();
+        match pivot {
+            None => {
+                if parity {
+                    self.inconsistent = true;
+                }
+            }
+            Some(pivot) => {
+                let index = self.xors.rows.len();
+                self.xors.rows.push(row);
+                self.xors.parities.push(parity);
+                self.xors.pivots.push(Some(pivot));
+                self.xors.eliminate(pivot, index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Examines the rows of the XOR matrix, returning the key to the explanation of some conflict, if found.
+    /// Otherwise, any literal implied by a row is valued, with the row as the explanation.
+    ///
+    /// A row with a valued pivot is first pivoted on some unvalued column, so each row examined alone finds every implied literal and every conflict.
+    pub fn propagate_xor(&mut self) -> Result<Option<ClauseKey>, SolverError> {
+        if self.xors.rows.is_empty() {
+            return Ok(None);
+        }
+
+        let words = self.xors.columns.len().div_ceil(64);
+        let mut unvalued = vec![0_u64; words];
+        let mut true_columns = vec![0_u64; words];
+        for (column, &v_id) in self.xors.columns.iter().enumerate() {
+            match self.valuation.of_v_id(v_id) {
+                None => unvalued[column / 64] |= 1 << (column % 64),
+                Some(true) => true_columns[column / 64] |= 1 << (column % 64),
+                Some(false) => {}
+            }
+        }
+
+        for index in 0..self.xors.rows.len() {
+            if let Some(pivot) = self.xors.pivots[index] {
+                if has_column(&unvalued, pivot) {
+                    continue;
+                }
+            }
+
+            let row = &self.xors.rows[index];
+            let unvalued_column = set_columns(&intersection(row, &unvalued)).next();
+            self.xors.pivots[index] = unvalued_column;
+            match unvalued_column {
+                Some(pivot) => self.xors.eliminate(pivot, index),
+                None => {
+                    if odd(&intersection(row, &true_columns)) != self.xors.parities[index] {
+                        let conflict = self.xor_explanation(index, None);
+                        return Ok(Some(self.store_xor_reason(conflict).0));
+                    }
+                }
+            }
+        }
+
+        for index in 0..self.xors.rows.len() {
+            let pivot = match self.xors.pivots[index] {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            let row = &self.xors.rows[index];
+            if set_columns(&intersection(row, &unvalued)).nth(1).is_some() {
+                continue;
+            }
+
+            let polarity = self.xors.parities[index] != odd(&intersection(row, &true_columns));
+            let implied = Literal::new(self.xors.columns[pivot], polarity);
+            let reason = self.xor_explanation(index, Some(implied));
+            let (key, level) = self.store_xor_reason(reason);
+            literal_update_at(
+                implied,
+                LiteralSource::StoredClause(key),
+                level,
+                &mut self.levels,
+                &mut self.decision_levels,
+                &mut self.valuation,
+                &mut self.trail,
+            )?;
+        }
+
+        Ok(None)
+    }
+
+    /// The clause of the row under the valuation, with the literal if given.
+    fn xor_explanation(&self, index: usize, implied: Option<Literal>) -> ClauseVec {
+        let mut the_clause = set_columns(&self.xors.rows[index])
+            .map(|column| self.xors.columns[column])
+            .filter_map(|v_id| {
+                self.valuation
+                    .of_v_id(v_id)
+                    .map(|value| Literal::new(v_id, !value))
+            })
+            .chain(implied)
+            .collect::<ClauseVec>();
+        the_clause.sort_unstable();
+        the_clause
+    }
+
+    /// Stores the explanation, together with the greatest level of the valued literals of the explanation, which is the level of any literal or conflict explained.
+    /// The explanation is kept until the level is undone.
+    fn store_xor_reason(&mut self, reason: ClauseVec) -> (ClauseKey, LevelIndex) {
+        let key = ClauseKey::Xor(self.xor_reasons.len() as u32);
+        let level = reason
+            .iter()
+            .filter_map(|literal| self.decision_levels[literal.v_id() as usize])
+            .max()
+            .unwrap_or(0);
+        self.xor_reasons.push((level, reason));
+        (key, level)
+    }
+}
+
+fn has_column(row: &[u64], column: usize) -> bool {
+    row[column / 64] & (1 << (column % 64)) != 0
+}
+
+fn intersection(a: &[u64], b: &[u64]) -> Vec<u64> {
+    a.iter().zip(b).map(|(x, y)| x & y).collect()
+}
+
+fn odd(row: &[u64]) -> bool {
+    row.iter().map(|word| word.count_ones()).sum::<u32>() % 2 == 1
+}
+
+fn set_columns(row: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    row.iter().enumerate().flat_map(|(index, &word)| {
+        (0..64)
+            .filter(move |bit| word & (1 << bit) != 0)
+            .map(move |bit| index * 64 + bit)
+    })
+}
+
+/// The rows at the two distinct indices, the first shared and the second mutable.
+fn pair_mut(rows: &mut [Vec<u64>], shared: usize, mutable: usize) -> (&[u64], &mut [u64]) {
+    if shared < mutable {
+        let (low, high) = rows.split_at_mut(mutable);
+        (&low[shared], &mut high[0])
+    } else {
+        let (low, high) = rows.split_at_mut(shared);
+        (&high[0], &mut low[mutable])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::solve::{test_formulas::solve_of, SolveResult};
+
+    #[test]
+    fn parity_chains() {
+        // 1 + 2 = 1, 2 + 3 = 1, and 1 + 3 = 1 sum to 0 = 1
+        let mut the_solve = solve_of("x1 2 0\nx2 3 0\nx1 3 0\n");
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+
+        // 1 + 2 + 3 = 0 and 3 + 4 = 1, with 4 forcing 3 and so 1 or 2 but not both
+        let mut the_solve = solve_of("x-1 2 3 0\nx3 4 0\n4 0\n");
+        assert_eq!(the_solve.count_models(None).unwrap().to_string(), "2");
+    }
+}
@@ -0,0 +1,331 @@
+use crate::{
+    errors::SolverError,
+    procedures::xorshift,
+    structures::{
+        clause::Clause,
+        literal::Literal,
+        solve::{config, ClauseKey, Solve, SolveResult},
+        valuation::{Valuation, ValuationVec},
+        variable::VariableId,
+    },
+};
+
+/// The count of flips of each local search made between restarts of a solve
+pub const LOCAL_SEARCH_FLIPS: usize = 100_000;
+
+/// The count of flips between each check of the limits of a local search
+const LIMIT_INTERVAL: usize = 1024;
+
+/// A variable is picked with chance weighted by (EPSILON + break)^-BREAK_BASE, as the polynomial break function of ProbSAT
+const BREAK_BASE: f64 = 2.3;
+const EPSILON: f64 = 1.0;
+
+/// The clauses of a local search, together with an assignment and the clauses false on the assignment.
+struct LocalSearch {
+    clauses: Vec<Vec<Literal>>,
+    /// For each literal, the clauses containing the literal
+    occurrences: Vec<Vec<usize>>,
+    /// For each variable, the value of the variable
+    assignment: Vec<bool>,
+    /// For each clause, the count of literals of the clause true on the assignment
+    true_counts: Vec<usize>,
+    /// The clauses false on the assignment
+    falsified: Vec<usize>,
+    /// For each clause, the position of the clause in the false clauses, if false
+    positions: Vec<Option<usize>>,
+    /// For each break up to some limit, the weight of a variable with the break
+    weights: Vec<f64>,
+    /// The state of an xorshift generator
+    state: u64,
+}
+
+impl LocalSearch {
+    fn new(clauses: Vec<Vec<Literal>>, assignment: Vec<bool>, seed: u64) -> Self {
+        let mut occurrences = vec![vec![]; 2 * assignment.len()];
+        for (index, clause) in clauses.iter().enumerate() {
+            for literal in clause {
+                occurrences[literal.index()].push(index);
+            }
+        }
+        let mut search = LocalSearch {
+            true_counts: vec![0; clauses.len()],
+            positions: vec![None; clauses.len()],
+            falsified: vec![],
+            clauses,
+            occurrences,
+            assignment,
+            weights: (0..64)
+                .map(|breaks| (EPSILON + breaks as f64).powf(-BREAK_BASE))
+                .collect(),
+            state: seed.max(1),
+        };
+        for index in 0..search.clauses.len() {
+            search.true_counts[index] = search.clauses[index]
+                .iter()
+                .filter(|literal| search.is_true(**literal))
+                .count();
+            if search.true_counts[index] == 0 {
+                search.falsify(index);
+            }
+        }
+        search
+    }
+
+    fn is_true(&self, literal: Literal) -> bool {
+        self.assignment[literal.v_id() as usize] == literal.polarity()
+    }
+
+    fn falsify(&mut self, clause: usize) {
+        self.positions[clause] = Some(self.falsified.len());
+        self.falsified.push(clause);
+    }
+
+    fn satisfy(&mut self, clause: usize) {
+        if let Some(position) = self.positions[clause].take() {
+            self.falsified.swap_remove(position);
+            if let Some(&moved) = self.falsified.get(position) {
+                self.positions[moved] = Some(position);
+            }
+        }
+    }
+
+    /// The count of clauses which would become false if the variable were flipped.
+    fn breaks(&self, v_id: VariableId) -> usize {
+        let literal = Literal::new(v_id, self.assignment[v_id as usize]);
+        self.occurrences[literal.index()]
+            .iter()
+            .filter(|&&clause| self.true_counts[clause] == 1)
+            .count()
+    }
+
+    fn weight(&self, breaks: usize) -> f64 {
+        match self.weights.get(breaks) {
+            Some(weight) => *weight,
+            None => (EPSILON + breaks as f64).powf(-BREAK_BASE),
+        }
+    }
+
+    fn flip(&mut self, v_id: VariableId) {
+        let made_false = Literal::new(v_id, self.assignment[v_id as usize]);
+        self.assignment[v_id as usize] = !self.assignment[v_id as usize];
+
+        for index in 0..self.occurrences[made_false.index()].len() {
+            let clause = self.occurrences[made_false.index()][index];
+            self.true_counts[clause] -= 1;
+            if self.true_counts[clause] == 0 {
+                self.falsify(clause);
+            }
+        }
+        let made_true = made_false.negate();
+        for index in 0..self.occurrences[made_true.index()].len() {
+            let clause = self.occurrences[made_true.index()][index];
+            self.true_counts[clause] += 1;
+            if self.true_counts[clause] == 1 {
+                self.satisfy(clause);
+            }
+        }
+    }
+
+    /// Flips a variable of some false clause, each picked at random.
+    fn step(&mut self) {
+        let position = xorshift(&mut self.state) as usize % self.falsified.len();
+        let clause = self.falsified[position];
+        let weights = self.clauses[clause]
+            .iter()
+            .map(|literal| self.weight(self.breaks(literal.v_id())))
+            .collect::<Vec<_>>();
+
+        let mut remaining = (xorshift(&mut self.state) >> 11) as f64 / (1_u64 << 53) as f64
+            * weights.iter().sum::<f64>();
+        let mut picked = self.clauses[clause].len() - 1;
+        for (position, weight) in weights.iter().enumerate() {
+            if remaining < *weight {
+                picked = position;
+                break;
+            }
+            remaining -= weight;
+        }
+        self.flip(self.clauses[clause][picked].v_id());
+    }
+}
+
+impl Solve {
+    /// Searches for an assignment which satisfies the clauses of the formula by at most the given count of flips, and saves the best assignment found as the phases of the solve.
+    ///
+    /// The search is by ProbSAT over the clauses of the formula simplified by the values of level 0, and the solve is left at the current level.
+    /// Learnt clauses follow from the formula, and cardinality and XOR constraints are left to the search of the solve, so neither are used.
+    /// A satisfiable result is only a model of the clauses, and a search of the solve then finds a model of the solve following the phases.
+    /// An unknown result is returned if no model of the clauses is found, or if the search is stopped or exceeds the time limit.
+    pub fn local_search(&mut self, max_flips: usize) -> Result<SolveResult, SolverError> {
+        let this_total_time = std::time::Instant::now();
+
+        if self.inconsistent {
+            return Ok(SolveResult::Unsatisfiable);
+        }
+        // above level 0 the trail is kept, and only the values of level 0 are used
+        if self.current_level().index() == 0 {
+            if let Some(conflict) = self.propagate()? {
+                self.refute(conflict);
+                return Ok(SolveResult::Unsatisfiable);
+            }
+        }
+        let fixed = self.valuation_at(0);
+
+        let clauses = self
+            .stored_clauses()
+            .filter(|stored_clause| matches!(stored_clause.key(), ClauseKey::Formula(_)))
+            .filter(|stored_clause| {
+                !stored_clause
+                    .literals()
+                    .any(|literal| fixed.of_literal(literal) == Some(true))
+            })
+            .map(|stored_clause| {
+                stored_clause
+                    .literals()
+                    .filter(|literal| fixed.of_literal(*literal).is_none())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut seed = self.config.seed.wrapping_add(self.restarts as u64 + 1);
+        let assignment = (0..self.variables.len() as VariableId)
+            .map(|v_id| {
+                let phase = self
+                    .saved_phases
+                    .as_ref()
+                    .and_then(|saved| saved.of_v_id(v_id));
+                match fixed.of_v_id(v_id).or(phase) {
+                    Some(value) => value,
+                    None => {
+                        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                        seed >> 63 == 1
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut search = LocalSearch::new(clauses, assignment, seed);
+        let mut best = search.assignment.clone();
+        let mut least_false = search.falsified.len();
+
+        for flip in 0..max_flips {
+            if search.falsified.is_empty() {
+                break;
+            }
+            if flip.is_multiple_of(LIMIT_INTERVAL) && flip > 0 {
+                let out_of_time = unsafe { config::TIME_LIMIT }
+                    .is_some_and(|limit| this_total_time.elapsed() > limit);
+                if out_of_time || self.is_interrupted() || self.terminate_requested() {
+                    break;
+                }
+            }
+            search.step();
+            if search.falsified.len() < least_false {
+                least_false = search.falsified.len();
+                best.clone_from(&search.assignment);
+            }
+        }
+
+        let mut phases = ValuationVec::new_for_variables(self.variables.len());
+        for (v_id, value) in best.into_iter().enumerate() {
+            let _ = phases.update_value(Literal::new(v_id as VariableId, value));
+        }
+        self.saved_phases = Some(phases);
+
+        match least_false {
+            0 => Ok(SolveResult::Satisfiable),
+            _ => Ok(SolveResult::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{
+        clause::stored_clause::ClauseSource,
+        literal::LiteralSource,
+        solve::{
+            config::SolveConfig,
+            test_formulas::{pigeonhole, solve_of},
+            the_solve::literal_update,
+        },
+    };
+
+    #[test]
+    fn phases_from_local_search() {
+        // a chain of implications from 1 to 8, together with 1 and some clauses satisfied by the chain
+        let mut dimacs = (1..8)
+            .map(|v| format!("-{v} {} 0\n", v + 1))
+            .collect::<String>();
+        dimacs.push_str("1 9 0\n-9 -8 3 0\n2 -5 9 0\n");
+        let mut the_solve = solve_of(&dimacs);
+        assert!(matches!(
+            the_solve.local_search(10_000).unwrap(),
+            SolveResult::Satisfiable
+        ));
+
+        let phases = the_solve.saved_phases.clone().unwrap();
+        assert!(the_solve
+            .stored_clauses()
+            .all(|stored_clause| stored_clause.is_sat_on(&phases)));
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Satisfiable
+        ));
+
+        the_solve
+            .add_clause(vec![Literal::new(7, false)], ClauseSource::Formula)
+            .unwrap();
+        the_solve
+            .add_clause(vec![Literal::new(0, true)], ClauseSource::Formula)
+            .unwrap();
+        assert!(matches!(
+            the_solve.local_search(10_000).unwrap(),
+            SolveResult::Unsatisfiable
+        ));
+    }
+
+    #[test]
+    fn local_search_keeps_the_trail() {
+        // the pigeonhole principle for four pigeons and three holes
+        let formula = pigeonhole(3);
+        let mut the_solve = Solve::from_formula(formula.clone()).unwrap();
+
+        for v_id in [0, 4] {
+            the_solve.add_fresh_level();
+            literal_update(
+                Literal::new(v_id, true),
+                LiteralSource::Choice,
+                &mut the_solve.levels,
+                &mut the_solve.decision_levels,
+                &mut the_solve.valuation,
+                &mut the_solve.trail,
+            )
+            .unwrap();
+        }
+        the_solve.propagate().unwrap();
+        let trail = the_solve.trail.clone();
+        assert!(matches!(
+            the_solve.local_search(1_000).unwrap(),
+            SolveResult::Unknown
+        ));
+        assert_eq!(the_solve.current_level().index(), 2);
+        assert_eq!(the_solve.trail, trail);
+
+        // a local search on each restart, with the trail reused
+        let mut the_solve = Solve::from_formula(formula).unwrap();
+        the_solve.set_config(SolveConfig {
+            restarts_allowed: true,
+            restart_unit: 1,
+            local_search: Some(1),
+            trail_reuse: true,
+            ..SolveConfig::default()
+        });
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+        assert!(the_solve.restarts > 0);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::{clause_vec::ClauseVec, stored_clause::ClauseSource},
+        literal::Literal,
+        solve::{Solve, SolveResult},
+        variable::VariableId,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxSatResult {
+    /// The least cost of a model, with the model as the valuation of the solve
+    Optimum(u64),
+    Unsatisfiable,
+    Unknown,
+}
+
+struct SoftClause {
+    clause: ClauseVec,
+    weight: u64,
+    selector: VariableId,
+}
+
+struct Relaxation {
+    soft_clauses: Vec<SoftClause>,
+    /// For each selector variable, the soft clause of the selector
+    selected: HashMap<VariableId, usize>,
+    /// Soft clauses which have been retired
+    retired: Vec<bool>,
+    cost: u64,
+}
+
+impl Solve {
+    /// Finds a model of the clauses of the solve which minimises the weight of the given soft clauses which are not satisfied.
+    ///
+    /// Soft clauses are added to the solve as clauses of the formula, each relaxed by some fresh variable.
+    /// The optimum is found by the core-guided algorithm of Fu and Malik, with weights as in WPM1, by solves under the negation of each selector.
+    pub fn minimise(
+        &mut self,
+        soft_clauses: Vec<(u64, ClauseVec)>,
+    ) -> Result<MaxSatResult, SolverError> {
+        let mut relaxation = Relaxation {
+            soft_clauses: vec![],
+            selected: HashMap::new(),
+            retired: vec![],
+            cost: 0,
+        };
+        for (weight, clause) in soft_clauses {
+            self.add_soft_clause(&mut relaxation, clause, weight)?;
+        }
+
+        loop {
+            let assumptions = relaxation
+                .soft_clauses
+                .iter()
+                .zip(&relaxation.retired)
+                .filter(|(_, &retired)| !retired)
+                .map(|(soft, _)| Literal::new(soft.selector, false))
+                .collect::<Vec<_>>();
+
+            match self.solve_under_assumptions(&assumptions)?.0 {
+                SolveResult::Satisfiable => return Ok(MaxSatResult::Optimum(relaxation.cost)),
+                SolveResult::Unknown => return Ok(MaxSatResult::Unknown),
+                SolveResult::Unsatisfiable => {
+                    let core = self
+                        .failed_assumptions()
+                        .iter()
+                        .filter_map(|literal| relaxation.selected.get(&literal.v_id()).copied())
+                        .collect::<Vec<_>>();
+                    if core.is_empty() {
+                        return Ok(MaxSatResult::Unsatisfiable);
+                    }
+                    self.relax_core(&mut relaxation, core)?;
+                }
+            }
+        }
+    }
+
+    /// Adds the clause together with a fresh selector variable, unless the clause is trivial.
+    fn add_soft_clause(
+        &mut self,
+        relaxation: &mut Relaxation,
+        clause: ClauseVec,
+        weight: u64,
+    ) -> Result<(), SolverError> {
+        let tautology = clause
+            .windows(2)
+            .any(|pair| pair[0].v_id() == pair[1].v_id());
+        if weight == 0 || tautology {
+            return Ok(());
+        }
+        if clause.is_empty() {
+            relaxation.cost += weight;
+            return Ok(());
+        }
+
+        let selector = self.fresh_variable(&format!("_s{}", relaxation.soft_clauses.len()));
+        let mut selectable = clause.clone();
+        selectable.push(Literal::new(selector, true));
+        self.add_clause(selectable, ClauseSource::Formula)?;
+
+        relaxation
+            .selected
+            .insert(selector, relaxation.soft_clauses.len());
+        relaxation.retired.push(false);
+        relaxation.soft_clauses.push(SoftClause {
+            clause,
+            weight,
+            selector,
+        });
+        Ok(())
+    }
+
+    /// Retires each soft clause of the core, with the least weight of the core added to the cost.
+    /// Any remaining weight is kept by a copy, and a copy relaxed by a fresh variable is added with the least weight, exactly one relaxation of the core being true.
+    fn relax_core(
+        &mut self,
+        relaxation: &mut Relaxation,
+        core: Vec<usize>,
+    ) -> Result<(), SolverError> {
+        let least_weight = core
+            .iter()
+            .map(|&index| relaxation.soft_clauses[index].weight)
+            .min()
+            .unwrap_or(0);
+
+        let mut relaxation_literals = vec![];
+        for index in core {
+            relaxation.retired[index] = true;
+            let selector = relaxation.soft_clauses[index].selector;
+            self.add_clause(vec![Literal::new(selector, true)], ClauseSource::Formula)?;
+
+            let clause = relaxation.soft_clauses[index].clause.clone();
+            let residual = relaxation.soft_clauses[index].weight - least_weight;
+            if residual > 0 {
+                self.add_soft_clause(relaxation, clause.clone(), residual)?;
+            }
+
+            let relaxer = self.fresh_variable(&format!("_r{}", self.variables.len()));
+            let mut relaxed = clause;
+            relaxed.push(Literal::new(relaxer, true));
+            relaxed.sort_unstable();
+            self.add_soft_clause(relaxation, relaxed, least_weight)?;
+            relaxation_literals.push(Literal::new(relaxer, true));
+        }
+
+        self.add_clause(relaxation_literals.clone(), ClauseSource::Formula)?;
+        self.add_at_most(relaxation_literals, 1)?;
+        relaxation.cost += least_weight;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::formula::Formula;
+
+    #[test]
+    fn weighted_optimum() {
+        // at most one of 1, 2, 3 may be true, with each true literal worth its weight
+        let mut formula = Formula::from_wcnf(
+            "p wcnf 3 6 100\n100 -1 -2 0\n100 -1 -3 0\n100 -2 -3 0\n4 1 0\n3 2 0\n2 3 0\n",
+        )
+        .unwrap();
+        let soft_clauses = std::mem::take(&mut formula.soft_clauses);
+        let mut the_solve = Solve::from_formula(formula).unwrap();
+        assert_eq!(
+            the_solve.minimise(soft_clauses).unwrap(),
+            MaxSatResult::Optimum(5)
+        );
+
+        let mut formula = Formula::from_wcnf("h 1 0\nh -1 0\n1 2 0\n").unwrap();
+        let soft_clauses = std::mem::take(&mut formula.soft_clauses);
+        let mut the_solve = Solve::from_formula(formula).unwrap();
+        assert_eq!(
+            the_solve.minimise(soft_clauses).unwrap(),
+            MaxSatResult::Unsatisfiable
+        );
+    }
+}
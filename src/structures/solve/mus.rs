@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::{clause_vec::ClauseVec, stored_clause::ClauseSource},
+        literal::Literal,
+        solve::{Solve, SolveResult},
+        variable::VariableId,
+    },
+};
+
+enum Refinement {
+    Satisfiable,
+    /// The candidates of the failed assumptions, in ascending order
+    Unsatisfiable(Vec<usize>),
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MusResult {
+    /// The indices of a minimal unsatisfiable subset of the clauses, in ascending order
+    Minimal(Vec<usize>),
+    Satisfiable,
+    Unknown,
+}
+
+impl Solve {
+    /// Finds a minimal subset of the given clauses which is unsatisfiable together with the clauses of the solve.
+    ///
+    /// The clauses are added to the solve as clauses of the formula, each relaxed by some fresh variable.
+    /// The subset is found by deletion with clause set refinement, where a candidate whose removal leaves the candidates satisfiable is kept.
+    pub fn minimal_unsatisfiable_subset(
+        &mut self,
+        clauses: Vec<ClauseVec>,
+    ) -> Result<MusResult, SolverError> {
+        let mut selectors = Vec::with_capacity(clauses.len());
+        let mut selected = HashMap::new();
+        for (index, mut clause) in clauses.into_iter().enumerate() {
+            let selector = self.fresh_variable(&format!("_m{index}"));
+            clause.push(Literal::new(selector, true));
+            self.add_clause(clause, ClauseSource::Formula)?;
+            selectors.push(selector);
+            selected.insert(selector, index);
+        }
+
+        let mut candidates = (0..selectors.len()).collect::<Vec<_>>();
+        match self.refine(&candidates, None, &selectors, &selected)? {
+            Refinement::Satisfiable => return Ok(MusResult::Satisfiable),
+            Refinement::Unknown => return Ok(MusResult::Unknown),
+            Refinement::Unsatisfiable(refined) => candidates = refined,
+        }
+
+        // each candidate before the position is necessary
+        let mut position = 0;
+        while position < candidates.len() {
+            let excluded = candidates[position];
+            match self.refine(&candidates, Some(excluded), &selectors, &selected)? {
+                Refinement::Satisfiable => position += 1,
+                Refinement::Unknown => return Ok(MusResult::Unknown),
+                Refinement::Unsatisfiable(refined) => {
+                    for dropped in candidates
+                        .iter()
+                        .filter(|candidate| refined.binary_search(candidate).is_err())
+                    {
+                        let selector = Literal::new(selectors[*dropped], true);
+                        self.add_clause(vec![selector], ClauseSource::Formula)?;
+                    }
+                    candidates = refined;
+                }
+            }
+        }
+        Ok(MusResult::Minimal(candidates))
+    }
+
+    /// Solves with each candidate other than any excluded candidate, and if unsatisfiable refines the candidates to those of the failed assumptions.
+    /// As a subset of a satisfiable set of clauses is satisfiable, refinement never drops a necessary clause.
+    fn refine(
+        &mut self,
+        candidates: &[usize],
+        excluded: Option<usize>,
+        selectors: &[VariableId],
+        selected: &HashMap<VariableId, usize>,
+    ) -> Result<Refinement, SolverError> {
+        let assumptions = candidates
+            .iter()
+            .filter(|&&candidate| Some(candidate) != excluded)
+            .map(|&candidate| Literal::new(selectors[candidate], false))
+            .collect::<Vec<_>>();
+
+        match self.solve_under_assumptions(&assumptions)?.0 {
+            SolveResult::Satisfiable => Ok(Refinement::Satisfiable),
+            SolveResult::Unknown => Ok(Refinement::Unknown),
+            SolveResult::Unsatisfiable => {
+                let mut refined = self
+                    .failed_assumptions()
+                    .iter()
+                    .filter_map(|literal| selected.get(&literal.v_id()).copied())
+                    .collect::<Vec<_>>();
+                refined.sort_unstable();
+                Ok(Refinement::Unsatisfiable(refined))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::formula::Formula;
+
+    #[test]
+    fn minimal_subset() {
+        // clauses 2 and 5 are satisfiable on variables of their own, and the subset has either clause 7 or both clauses 4 and 6
+        let dimacs = "1 2 0\n-1 2 0\n5 6 0\n1 -2 0\n-1 -2 3 0\n-5 6 0\n-3 0\n-1 -2 0\n";
+        let mut formula = Formula::from_dimacs(dimacs).unwrap();
+        let clauses = std::mem::take(&mut formula.clauses);
+        let mut the_solve = Solve::from_formula(formula.clone()).unwrap();
+        let mus = match the_solve
+            .minimal_unsatisfiable_subset(clauses.clone())
+            .unwrap()
+        {
+            MusResult::Minimal(mus) => mus,
+            _ => panic!("No subset found"),
+        };
+        assert!(!mus.contains(&2) && !mus.contains(&5));
+
+        // without any one clause of the subset, the remaining clauses of the subset are satisfiable
+        for dropped in &mus {
+            let subset = mus
+                .iter()
+                .filter(|index| *index != dropped)
+                .map(|index| clauses[*index].clone())
+                .collect();
+            let mut the_solve = Solve::from_formula(formula.clone()).unwrap();
+            assert_eq!(
+                the_solve.minimal_unsatisfiable_subset(subset).unwrap(),
+                MusResult::Satisfiable
+            );
+        }
+    }
+}
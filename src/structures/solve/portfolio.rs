@@ -0,0 +1,342 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver, SyncSender},
+    Arc,
+};
+
+use crate::{
+    errors::SolverError,
+    structures::{
+        clause::{clause_vec::ClauseVec, stored_clause::ClauseSource},
+        formula::Formula,
+        level::LevelIndex,
+        literal::{Literal, LiteralSource},
+        solve::{
+            config::SolveConfig,
+            the_solve::{literal_update, literal_update_at},
+            ClauseKey, Solve, SolveResult,
+        },
+        valuation::Valuation,
+    },
+};
+
+/// The greatest length of a learnt clause to share
+const SHARED_LENGTH: usize = 8;
+/// The greatest lbd of a learnt clause to share
+const SHARED_LBD: usize = 3;
+/// The greatest count of clauses waiting to be received by a solve, past which clauses sent to the solve are dropped
+const EXCHANGE_BOUND: usize = 4096;
+
+/// Senders to every other solve of a portfolio, and a receiver of the clauses sent by each other solve.
+///
+/// A clause sent to a solve whose channel is full is dropped, so a solve never waits on another.
+pub struct ClauseExchange {
+    senders: Vec<SyncSender<ClauseVec>>,
+    receiver: Receiver<ClauseVec>,
+}
+
+impl ClauseExchange {
+    /// An exchange for each of the given count of solves, with each exchange sending to every other exchange.
+    pub fn network(size: usize) -> Vec<ClauseExchange> {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..size)
+            .map(|_| mpsc::sync_channel(EXCHANGE_BOUND))
+            .unzip();
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(index, receiver)| ClauseExchange {
+                senders: senders
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, sender)| sender.clone())
+                    .collect(),
+                receiver,
+            })
+            .collect()
+    }
+}
+
+impl Solve {
+    /// Stops the solve with an unknown result once the flag is raised.
+    pub fn set_interrupt(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    pub fn set_exchange(&mut self, exchange: ClauseExchange) {
+        self.exchange = Some(exchange);
+    }
+
+    /// Whether the interrupt flag of the solve, if any, has been raised.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Sends a learnt clause to every other solve of the exchange, if the clause is short enough and of low enough glue.
+    pub fn export_clause(&self, clause: &ClauseVec, lbd: usize) {
+        if let Some(exchange) = &self.exchange {
+            if clause.len() <= SHARED_LENGTH && lbd <= SHARED_LBD {
+                for sender in &exchange.senders {
+                    // a solve which has stopped no longer receives, and a solve with a full channel is behind, so either may be ignored
+                    let _ = sender.try_send(clause.clone());
+                }
+            }
+        }
+    }
+
+    /// Adds each clause received from other solves at the current level, returning the key to some clause which conflicts with the valuation, if any.
+    /// A received clause is learnt from no other clause, and so is not part of an unsatisfiable core.
+    ///
+    /// A clause unit on the valuation has the implied literal set at the greatest level of the other literals, and so the trail is kept.
+    /// Only a unit clause whose literal is valued above level 0 requires a backjump, to below the level of the literal.
+    pub fn import_clauses(&mut self) -> Result<Option<ClauseKey>, SolverError> {
+        let mut received = match &self.exchange {
+            Some(exchange) => exchange.receiver.try_iter().collect::<Vec<_>>(),
+            None => return Ok(None),
+        };
+        // a unit clause may require a backjump, and so is added before any clause which may conflict
+        received.sort_by_key(|clause| clause.len());
+        if !received.is_empty() {
+            self.origins_tracked = false;
+        }
+        let mut conflict = None;
+        for clause in received {
+            if let Some(key) = self.import_clause(clause)? {
+                conflict.get_or_insert(key);
+            }
+        }
+        Ok(conflict)
+    }
+
+    fn import_clause(&mut self, mut clause: ClauseVec) -> Result<Option<ClauseKey>, SolverError> {
+        clause.sort_unstable();
+        clause.dedup();
+
+        if clause.is_empty() {
+            self.note_inconsistency(vec![]);
+            return Ok(None);
+        }
+        if let [literal] = clause[..] {
+            if let Some(level) = self.decision_levels[literal.v_id() as usize] {
+                match level {
+                    0 => {
+                        if self.valuation.of_literal(literal) == Some(false) {
+                            self.note_inconsistency(vec![]);
+                        }
+                        return Ok(None);
+                    }
+                    _ => self.backjump(level - 1),
+                }
+            }
+            literal_update(
+                literal,
+                LiteralSource::Resolution(vec![]),
+                &mut self.levels,
+                &mut self.decision_levels,
+                &mut self.valuation,
+                &mut self.trail,
+            )?;
+            return Ok(None);
+        }
+
+        // unvalued literals first, then true literals, then false literals from the greatest level, so the initial watches are the first two literals
+        // and, if the clause is unit or conflicts, a backjump unvalues a watch before any other literal
+        let level_of = |literal: &Literal| self.decision_levels[literal.v_id() as usize];
+        clause.sort_by_key(|literal| match self.valuation.of_literal(*literal) {
+            None => (0, 0),
+            Some(true) => (1, 0),
+            Some(false) => (2, LevelIndex::MAX - level_of(literal).unwrap_or(0)),
+        });
+        let implied = clause[0];
+        let first = self.valuation.of_literal(clause[0]);
+        let second = self.valuation.of_literal(clause[1]);
+        let second_level = level_of(&clause[1]).unwrap_or(0);
+
+        let key = self.store_clause(clause, ClauseSource::Resolution(vec![]))?;
+        match (first, second) {
+            (Some(false), _) => Ok(Some(key)),
+            (None, Some(false)) => {
+                literal_update_at(
+                    implied,
+                    LiteralSource::StoredClause(key),
+                    second_level,
+                    &mut self.levels,
+                    &mut self.decision_levels,
+                    &mut self.valuation,
+                    &mut self.trail,
+                )?;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The configuration of the solve of the portfolio with the given index.
+/// The first solve has the configuration given by the global configuration variables, and each other solve restarts, with a varied restart unit, default polarity, and seed.
+fn portfolio_config(index: usize) -> SolveConfig {
+    let mut config = SolveConfig::default();
+    if index > 0 {
+        config.restarts_allowed = true;
+        config.restart_unit = [64, 128, 256, 512][index % 4];
+        config.default_polarity = index % 2 == 1;
+        config.seed = index as u64;
+    }
+    config
+}
+
+/// Solves the formula with a portfolio of the given count of solves, each on a thread of its own.
+///
+/// Returns the result of the first solve to find a result, together with the solve, as the first result stops every other solve.
+/// A solve which fails or panics does not stop any other solve, and the failure is returned only if no solve finds a result.
+/// If no solve finds a result and no solve fails, the result is unknown and the solve is the first solve of the portfolio.
+pub fn solve_portfolio(
+    formula: Formula,
+    threads: usize,
+) -> Result<(SolveResult, Solve), SolverError> {
+    let threads = threads.max(1);
+    let found = Arc::new(AtomicBool::new(false));
+    let exchanges = ClauseExchange::network(threads);
+
+    let outcomes = std::thread::scope(|scope| {
+        let handles = exchanges
+            .into_iter()
+            .enumerate()
+            .map(|(index, exchange)| {
+                let formula = formula.clone();
+                let found = found.clone();
+                scope.spawn(move || -> Result<(bool, SolveResult, Solve), SolverError> {
+                    let mut the_solve = Solve::from_formula(formula)?;
+                    the_solve.set_config(portfolio_config(index));
+                    the_solve.set_interrupt(found.clone());
+                    the_solve.set_exchange(exchange);
+
+                    let (result, _) = the_solve.do_solve()?;
+                    let first = match result {
+                        SolveResult::Unknown => false,
+                        _ => !found.swap(true, Ordering::Relaxed),
+                    };
+                    // clauses sent to the solve are of no further use
+                    the_solve.exchange = None;
+                    Ok((first, result, the_solve))
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or(Err(SolverError::PortfolioPanic)))
+            .collect::<Vec<_>>()
+    });
+
+    portfolio_outcome(outcomes)
+}
+
+/// The outcome of the solve which found a result first, else the first failure, else the outcome of the first solve.
+fn portfolio_outcome(
+    outcomes: Vec<Result<(bool, SolveResult, Solve), SolverError>>,
+) -> Result<(SolveResult, Solve), SolverError> {
+    let mut failure = None;
+    let mut unknown = None;
+    for outcome in outcomes {
+        match outcome {
+            Ok((true, result, the_solve)) => return Ok((result, the_solve)),
+            Ok((false, result, the_solve)) => {
+                unknown.get_or_insert((result, the_solve));
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    match (failure, unknown) {
+        (Some(e), _) => Err(e),
+        (None, Some(outcome)) => Ok(outcome),
+        (None, None) => Err(SolverError::PortfolioPanic),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{
+        clause::Clause,
+        solve::test_formulas::{pigeonhole, solve_of},
+    };
+
+    #[test]
+    fn portfolio_agrees() {
+        // the pigeonhole principle for three pigeons and two holes
+        let (result, _) = solve_portfolio(pigeonhole(2), 4).unwrap();
+        assert!(matches!(result, SolveResult::Unsatisfiable));
+
+        let formula = Formula::from_dimacs("1 2 0\n-1 3 0\n-2 -3 0\n").unwrap();
+        let (result, the_solve) = solve_portfolio(formula, 3).unwrap();
+        assert!(matches!(result, SolveResult::Satisfiable));
+        assert!(the_solve.stored_clauses().all(|clause| clause
+            .literals()
+            .any(|literal| the_solve.valuation.of_literal(literal) == Some(true))));
+    }
+
+    #[test]
+    fn failures_give_way_to_results() {
+        let (result, _) = portfolio_outcome(vec![
+            Err(SolverError::PortfolioPanic),
+            Ok((false, SolveResult::Unknown, solve_of("1 0\n"))),
+            Ok((true, SolveResult::Satisfiable, solve_of("1 0\n"))),
+        ])
+        .unwrap();
+        assert!(matches!(result, SolveResult::Satisfiable));
+
+        let outcome = portfolio_outcome(vec![
+            Ok((false, SolveResult::Unknown, solve_of("1 0\n"))),
+            Err(SolverError::PortfolioPanic),
+        ]);
+        assert!(matches!(outcome, Err(SolverError::PortfolioPanic)));
+    }
+
+    #[test]
+    fn imports_keep_the_trail() {
+        let mut the_solve = solve_of("1 2 3 4 0\n");
+        let mut exchanges = ClauseExchange::network(2);
+        let other = exchanges.pop().unwrap();
+        the_solve.set_exchange(exchanges.pop().unwrap());
+
+        for v_id in 0..2 {
+            the_solve.add_fresh_level();
+            literal_update(
+                Literal::new(v_id, false),
+                LiteralSource::Choice,
+                &mut the_solve.levels,
+                &mut the_solve.decision_levels,
+                &mut the_solve.valuation,
+                &mut the_solve.trail,
+            )
+            .unwrap();
+        }
+
+        // unit on the valuation, and so 3 is implied at level 1, below the current level
+        let sender = &other.senders[0];
+        sender
+            .send(vec![Literal::new(0, true), Literal::new(2, true)])
+            .unwrap();
+        assert_eq!(the_solve.import_clauses().unwrap(), None);
+        assert_eq!(the_solve.current_level().index(), 2);
+        assert_eq!(the_solve.decision_levels[2], Some(1));
+
+        // false on the valuation
+        sender
+            .send(vec![Literal::new(0, true), Literal::new(1, true)])
+            .unwrap();
+        let conflict = the_solve.import_clauses().unwrap();
+        assert!(conflict.is_some());
+        assert_eq!(the_solve.current_level().index(), 2);
+
+        // a unit clause valued above level 0 is set at level 0 after a backjump
+        sender.send(vec![Literal::new(1, true)]).unwrap();
+        assert_eq!(the_solve.import_clauses().unwrap(), None);
+        assert_eq!(the_solve.current_level().index(), 1);
+        assert_eq!(the_solve.decision_levels[1], Some(0));
+    }
+}
@@ -1,6 +1,7 @@
 pub static mut PROPAGATION_TIME: std::time::Duration = std::time::Duration::new(0, 0);
 pub static mut CONFLICT_TIME: std::time::Duration = std::time::Duration::new(0, 0);
 pub static mut REDUCTION_TIME: std::time::Duration = std::time::Duration::new(0, 0);
+pub static mut LOCAL_SEARCH_TIME: std::time::Duration = std::time::Duration::new(0, 0);
 pub static mut CHOICE_TIME: std::time::Duration = std::time::Duration::new(0, 0);
 pub static mut LITERAL_UPDATE_TIME: std::time::Duration = std::time::Duration::new(0, 0);
 pub static mut PROCESS_WATCH_TIME: std::time::Duration = std::time::Duration::new(0, 0);
@@ -43,13 +44,14 @@ impl std::fmt::Display for SolveStats {
         writeln!(f, "c       CLAUSE LOOP:       {:.2?}", unsafe {CLAUSE_LOOP_TIME})?;
         writeln!(f, "c         GET STORED:      {:.2?}", unsafe {GET_STORED_TIME})?;
         writeln!(f, "c         WATCH CHOICE:    {:.2?}", unsafe {WATCH_CHOICES_TIME})?;
-        writeln!(f, "c         LITERAL UPDATE:  {:.2?}", unsafe {PROCESS_WATCH_TIME})?;
+        writeln!(f, "c         LITERAL UPDATE:  {:.2?}", unsafe {LITERAL_UPDATE_TIME})?;
         writeln!(f, "c           PROCESS WATCH: {:.2?}", unsafe {PROCESS_WATCH_TIME})?;
         writeln!(f, "c             NEW WATCH:     {:.2?}", unsafe {NEW_WATCH_TIME})?;
         writeln!(f, "c             UPDATE WATCH:  {:.2?}", unsafe {UPDATE_WATCH_TIME})?;
         writeln!(f, "c     CONFLICT:    {:.2?}", unsafe {CONFLICT_TIME})?;
         writeln!(f, "c     CHOICE:      {:.2?}", unsafe {CHOICE_TIME})?;
         writeln!(f, "c       REDUCTION: {:.2?}", unsafe {REDUCTION_TIME})?;
+        writeln!(f, "c       LOCAL SEARCH: {:.2?}", unsafe {LOCAL_SEARCH_TIME})?;
         }
         Ok(())
     }
@@ -0,0 +1,47 @@
+use crate::{
+    procedures::xorshift,
+    structures::{formula::Formula, solve::Solve},
+};
+
+/// A solve of the formula read from the DIMACS.
+pub fn solve_of(dimacs: &str) -> Solve {
+    Solve::from_formula(Formula::from_dimacs(dimacs).unwrap()).unwrap()
+}
+
+/// The pigeonhole principle for one more pigeon than holes, with pigeon p in hole h by the variable `p * holes + h`, from pigeon 0 and hole 1.
+pub fn pigeonhole(holes: usize) -> Formula {
+    let mut dimacs = String::new();
+    for pigeon in 0..=holes {
+        let choices = (1..=holes).map(|hole| (pigeon * holes + hole).to_string());
+        dimacs.push_str(&format!("{} 0\n", choices.collect::<Vec<_>>().join(" ")));
+    }
+    for hole in 1..=holes {
+        for pigeon in 0..=holes {
+            for other in (pigeon + 1)..=holes {
+                dimacs.push_str(&format!(
+                    "-{} -{} 0\n",
+                    pigeon * holes + hole,
+                    other * holes + hole
+                ));
+            }
+        }
+    }
+    Formula::from_dimacs(&dimacs).unwrap()
+}
+
+/// A random formula of three literals to each clause, from the given seed.
+pub fn random_formula(variables: u64, clauses: usize, mut state: u64) -> Formula {
+    let mut dimacs = String::new();
+    for _ in 0..clauses * 3 {
+        xorshift(&mut state);
+        let variable = (state >> 1) % variables + 1;
+        match state & 1 {
+            0 => dimacs.push_str(&format!("{variable} ")),
+            _ => dimacs.push_str(&format!("-{variable} ")),
+        }
+        if dimacs.split_whitespace().count() % 4 == 3 {
+            dimacs.push_str("0\n");
+        }
+    }
+    Formula::from_dimacs(&dimacs).unwrap()
+}
@@ -1,18 +1,19 @@
+use std::collections::HashSet;
+
+use crate::errors::SolverError;
 use crate::procedures::hobson_choices;
 use crate::structures::{
-    clause::{
-        stored_clause::{ClauseStatus, StoredClause, Watch, WatchUpdate},
-        Clause,
-    },
-    level::Level,
+    clause::stored_clause::{Watch, WatchUpdate},
+    level::{Level, LevelIndex},
     literal::{Literal, LiteralSource},
     solve::{
-        config, retreive, retreive_mut,
+        config,
+        local_search::LOCAL_SEARCH_FLIPS,
         stats::SolveStats,
-        ClauseKey, ClauseStore, Solve, {SolveResult, SolveStatus},
+        ClauseKey, Solve, {SolveResult, SolveStatus},
     },
-    valuation::{Valuation, ValuationStatus},
-    variable::{Variable, VariableId},
+    valuation::Valuation,
+    variable::VariableId,
 };
 
 #[allow(unused_imports)] // used in timing macros
@@ -25,6 +26,7 @@ macro_rules! time_statement {
         let this_time = std::time::Instant::now();
         $s
         #[cfg(feature = "time")]
+        #[allow(unused_unsafe)]
         unsafe {
             $id += this_time.elapsed();
         }
@@ -46,24 +48,42 @@ macro_rules! time_block {
 
 impl Solve {
     #[allow(unused_labels)]
-    pub fn do_solve(&mut self) -> (SolveResult, SolveStats) {
+    pub fn do_solve(&mut self) -> Result<(SolveResult, SolveStats), SolverError> {
         let this_total_time = std::time::Instant::now();
 
         let mut stats = SolveStats::new();
-        let mut last_valuation = None;
+
+        self.failed_assumptions.clear();
+        if self.current_level().index() > 0 {
+            self.backjump(0);
+        }
+        self.begin_search();
 
         if unsafe { config::HOBSON_CHOICES } {
+            // as a variable of an XOR constraint may be either true or false, it is treated as occurring in both polarities
+            let xor_literals = self
+                .xors
+                .variables()
+                .iter()
+                .map(|&v_id| vec![Literal::new(v_id, false), Literal::new(v_id, true)]);
             let lits = self
                 .stored_clauses()
-                .map(|stored_clause| stored_clause.literals());
+                .map(|stored_clause| stored_clause.clause_clone())
+                .chain(xor_literals)
+                .map(|literals| literals.into_iter());
             let (f, t) = hobson_choices(lits);
-            self.literal_set_from_vec(f);
-            self.literal_set_from_vec(t);
+            self.literal_set_from_vec(f)?;
+            self.literal_set_from_vec(t)?;
         }
 
         let result: SolveResult;
 
         'main_loop: loop {
+            if self.inconsistent {
+                result = SolveResult::Unsatisfiable;
+                break 'main_loop;
+            }
+
             stats.total_time = this_total_time.elapsed();
             if let Some(time) = unsafe { config::TIME_LIMIT } {
                 if stats.total_time > time {
@@ -75,65 +95,23 @@ impl Solve {
                 }
             }
 
-            stats.iterations += 1;
-
-            let mut found_conflict = None;
-
-            'propagation_loop: while let Some((literal, source)) = self.watch_q.pop_front() {
-                let the_variable = &self.variables[literal.v_id()];
-
-                unsafe {
-                    let borrowed_occurrences = match literal.polarity {
-                        true => &mut *the_variable.negative_watch_occurrences.get(),
-                        false => &mut *the_variable.positive_watch_occurrences.get(),
-                    };
-
-                    let mut index = 0;
-                    let mut length = borrowed_occurrences.len();
-
-                    'clause_loop: while index < length {
-                        let clause_key = *borrowed_occurrences.get_unchecked(index);
-
-                        let stored_clause =
-                            retreive(&self.formula_clauses, &self.learnt_clauses, clause_key);
+            if self.stop_requested() {
+                result = SolveResult::Unknown;
+                break 'main_loop;
+            }
 
-                        let watch_choices =
-                            stored_clause.watch_status(&self.valuation, the_variable.id());
+            stats.iterations += 1;
 
-                        let clause_key = stored_clause.key();
+            // clauses from other solves are added at the current level, and may conflict with the valuation
+            let mut found_conflict = self.import_clauses()?;
+            if found_conflict.is_none() {
+                time_statement!(stats::PROPAGATION_TIME, found_conflict = self.propagate()?);
+            }
 
-                        match watch_choices {
-                            ClauseStatus::Missing => {
-                                borrowed_occurrences.swap_remove(index);
-                                length -= 1;
-                            }
-                            ClauseStatus::Entails(consequent) => {
-                                literal_update(
-                                    consequent,
-                                    LiteralSource::StoredClause(clause_key),
-                                    &mut self.levels,
-                                    &self.variables,
-                                    &mut self.valuation,
-                                    &mut self.formula_clauses,
-                                    &mut self.learnt_clauses,
-                                );
-                                self.watch_q.push_back((
-                                    consequent,
-                                    LiteralSource::StoredClause(clause_key),
-                                ));
-                                index += 1;
-                            }
-                            ClauseStatus::Conflict => {
-                                found_conflict = Some(clause_key);
-                                self.watch_q.clear();
-                                break 'clause_loop;
-                            }
-                            ClauseStatus::Unsatisfied | ClauseStatus::Satisfied => {
-                                index += 1;
-                            }
-                        }
-                    }
-                }
+            // a stopped propagation may have missed a conflict
+            if self.is_stopped() {
+                result = SolveResult::Unknown;
+                break 'main_loop;
             }
 
             match found_conflict {
@@ -141,64 +119,121 @@ impl Solve {
                     #[cfg(feature = "time")]
                     let this_choice_time = std::time::Instant::now();
 
-                    if unsafe { config::REDUCTION_ALLOWED } && self.it_is_time_to_reduce() {
-                        log::debug!(target: "forget", "{stats} @r {}", self.restarts);
-
-                        time_block!(stats::REDUCTION_TIME, {
-                            // // TODO: figure some improvement…
+                    if self.config.restarts_allowed && self.it_is_time_to_reduce() {
+                        self.saved_phases = Some(self.valuation.clone());
+                        self.backjump(self.restart_level());
+                        self.restarts += 1;
 
-                            let limit = self.learnt_clauses.len();
-                            let mut keys_to_drop = vec![];
-                            for (k, v) in &self.learnt_clauses {
-                                if keys_to_drop.len() > limit {
-                                    break;
-                                } else if v.get_set_lbd() > unsafe { config::GLUE_STRENGTH } {
-                                    keys_to_drop.push(k);
+                        if unsafe { config::REDUCTION_ALLOWED } {
+                            log::debug!(target: "forget", "{stats} @r {}", self.restarts);
+
+                            time_block!(stats::REDUCTION_TIME, {
+                                // // TODO: figure some improvement…
+
+                                // reasons for literals must be kept, and as the trail may be reused these may be at any level
+                                let locked = self
+                                    .levels
+                                    .iter()
+                                    .flat_map(|level| level.observations())
+                                    .filter_map(|(source, _)| match source {
+                                        LiteralSource::StoredClause(key) => Some(*key),
+                                        _ => None,
+                                    })
+                                    .collect::<HashSet<_>>();
+
+                                let keys_to_drop = self
+                                    .clause_store
+                                    .keys()
+                                    .filter(|key| {
+                                        matches!(key, ClauseKey::Learnt(_))
+                                            && self.clause_store.length(*key) > 2
+                                            && self.clause_store.lbd(*key)
+                                                > unsafe { config::GLUE_STRENGTH }
+                                            && !locked.contains(key)
+                                    })
+                                    .collect::<Vec<_>>();
+
+                                for key in keys_to_drop {
+                                    self.drop_learnt_clause(key)?
+                                }
+                                if self.clause_store.garbage() > 0 {
+                                    self.collect_garbage()?;
                                 }
-                            }
 
-                            for key in keys_to_drop {
-                                self.drop_learnt_clause(ClauseKey::Learnt(key))
-                            }
+                                log::debug!(target: "forget", "Reduced to: {}", self.clause_store.learnt_count());
+                            });
+                        }
+
+                        self.conflicts_since_last_forget = 0;
 
-                            log::debug!(target: "forget", "Reduced to: {}", self.learnt_clauses.len());
-                        });
+                        // on occasion, the phases are found by a local search from the phases of the restart
+                        // the local search keeps the trail, and as propagation was complete the result is only of use for the phases
+                        if self
+                            .config
+                            .local_search
+                            .is_some_and(|interval| self.restarts.is_multiple_of(interval))
+                        {
+                            time_statement!(
+                                stats::LOCAL_SEARCH_TIME,
+                                let _ = self.local_search(LOCAL_SEARCH_FLIPS)?
+                            );
+                        }
                     }
 
-                    if unsafe { config::RESTARTS_ALLOWED } && self.it_is_time_to_reduce() {
-                        last_valuation = Some(self.valuation.clone());
-                        self.backjump(0);
-                        self.restarts += 1;
-                        self.conflicts_since_last_forget = 0;
+                    // assumptions are chosen before any other literal, each on a level of its own
+                    while self.current_level().index() < self.assumptions.len() {
+                        let assumption = self.assumptions[self.current_level().index()];
+                        match self.valuation.of_literal(assumption) {
+                            Some(true) => {
+                                self.add_fresh_level();
+                            }
+                            Some(false) => {
+                                self.failed_assumptions = self.analyze_final(assumption);
+                                result = SolveResult::Unsatisfiable;
+                                break 'main_loop;
+                            }
+                            None => {
+                                self.add_fresh_level();
+                                literal_update(
+                                    assumption,
+                                    LiteralSource::Choice,
+                                    &mut self.levels,
+                                    &mut self.decision_levels,
+                                    &mut self.valuation,
+                                    &mut self.trail,
+                                )?;
+                                continue 'main_loop;
+                            }
+                        }
                     }
 
                     if let Some(available_v_id) = self.most_active_none(&self.valuation) {
                         log::trace!(
                             "Choice: {available_v_id} @ {} with activity {}",
                             self.current_level().index(),
-                            self.variables[available_v_id].activity()
+                            self.activity[available_v_id]
                         );
                         let _new_level = self.add_fresh_level();
-                        let choice_literal = if let Some(previous) = &last_valuation {
-                            if let Some(polarity) = previous[available_v_id] {
+                        let choice_literal = if let Some(previous) = &self.saved_phases {
+                            if let Some(polarity) = previous.of_v_id(available_v_id as VariableId) {
                                 Literal::new(available_v_id as VariableId, polarity)
                             } else {
-                                Literal::new(available_v_id as VariableId, false)
+                                Literal::new(
+                                    available_v_id as VariableId,
+                                    self.config.default_polarity,
+                                )
                             }
                         } else {
-                            Literal::new(available_v_id as VariableId, false)
+                            Literal::new(available_v_id as VariableId, self.config.default_polarity)
                         };
                         literal_update(
                             choice_literal,
                             LiteralSource::Choice,
                             &mut self.levels,
-                            &self.variables,
+                            &mut self.decision_levels,
                             &mut self.valuation,
-                            &mut self.formula_clauses,
-                            &mut self.learnt_clauses,
-                        );
-                        self.watch_q
-                            .push_back((choice_literal, LiteralSource::Choice));
+                            &mut self.trail,
+                        )?;
                         #[cfg(feature = "time")]
                         unsafe {
                             stats::CHOICE_TIME += this_choice_time.elapsed();
@@ -221,13 +256,13 @@ impl Solve {
                     self.conflicts_since_last_forget += 1;
                     self.conflicts_since_last_reset += 1;
 
-                    if self.conflicts % config::DECAY_FREQUENCY == 0 {
-                        for variable in &self.variables {
-                            variable.multiply_activity(config::DECAY_FACTOR);
+                    if self.conflicts.is_multiple_of(config::DECAY_FREQUENCY) {
+                        for activity in &mut self.activity {
+                            *activity *= config::DECAY_FACTOR;
                         }
                     }
 
-                    let analysis_result = self.attempt_fix(clause_key);
+                    let analysis_result = self.attempt_fix(clause_key)?;
                     stats.conflicts += 1;
                     #[cfg(feature = "time")]
                     unsafe {
@@ -235,162 +270,453 @@ impl Solve {
                     }
                     match analysis_result {
                         SolveStatus::NoSolution => {
+                            self.inconsistent = true;
                             result = SolveResult::Unsatisfiable;
                             break 'main_loop;
                         }
                         SolveStatus::AssertingClause => {
                             continue 'main_loop;
                         }
+                        SolveStatus::Stopped => {
+                            result = SolveResult::Unknown;
+                            break 'main_loop;
+                        }
                     }
                 }
             }
         }
         // loop exit
+        self.end_search();
         stats.total_time = this_total_time.elapsed();
-        match result {
-            SolveResult::Satisfiable => {
-                if unsafe { config::SHOW_ASSIGNMENT } {
-                    println!("c ASSIGNMENT: {}", self.valuation.as_display_string(self))
-                }
-            }
-            SolveResult::Unsatisfiable => {
-                if unsafe { config::SHOW_CORE } {
-                    self.core()
-                }
+        if let SolveResult::Satisfiable = result {
+            if unsafe { config::SHOW_ASSIGNMENT } {
+                println!("c ASSIGNMENT: {}", self.valuation.as_display_string(self))
             }
-            SolveResult::Unknown => {}
         }
-        (result, stats)
+        Ok((result, stats))
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn literal_update(
-    literal: Literal,
-    source: LiteralSource,
-    levels: &mut [Level],
-    variables: &[Variable],
-    valuation: &mut impl Valuation,
-    formula_clauses: &mut ClauseStore,
-    learnt_clauses: &mut ClauseStore,
-) {
-    let variable = unsafe { variables.get_unchecked(literal.v_id()) };
-
-    // update the valuation and match the result
-    match valuation.update_value(literal) {
-        Ok(()) => {
-            log::trace!("Set {source:?}: {literal}");
-            // if update occurrs, make records at the relevant level
+impl Solve {
+    /// Solves with the given literals assumed.
+    /// If the solve is unsatisfiable under the assumptions, some assumptions which suffice for this are noted, see [Solve::failed_assumptions].
+    pub fn solve_under_assumptions(
+        &mut self,
+        assumptions: &[Literal],
+    ) -> Result<(SolveResult, SolveStats), SolverError> {
+        self.assumptions = assumptions.to_vec();
+        let outcome = self.do_solve();
+        self.assumptions.clear();
+        outcome
+    }
 
-            unsafe {
-                {
-                    let level_index = match &source {
-                        LiteralSource::Choice | LiteralSource::StoredClause(_) => levels.len() - 1,
-                        LiteralSource::Assumption
-                        | LiteralSource::HobsonChoice
-                        | LiteralSource::Resolution(_) => 0,
-                    };
-                    variable.set_decision_level(level_index);
-                    levels
-                        .get_unchecked_mut(level_index)
-                        .record_literal(literal, &source);
+    /// The assumptions used to show the most recent solve was unsatisfiable, if the solve was unsatisfiable due to the assumptions.
+    pub fn failed_assumptions(&self) -> &[Literal] {
+        &self.failed_assumptions
+    }
+
+    /// Propagates each literal on the trail from the propagation head, returning the key to some conflicting clause, if found.
+    ///
+    /// The clauses are propagated to a fixpoint before the XOR constraints are examined, and this repeats until neither values a literal.
+    /// If the search is stopped, propagation ends without a conflict, see [Solve::is_stopped].
+    pub fn propagate(&mut self) -> Result<Option<ClauseKey>, SolverError> {
+        loop {
+            if let Some(conflict) = self.propagate_clauses()? {
+                return Ok(Some(conflict));
+            }
+            if self.is_stopped() {
+                return Ok(None);
+            }
+            if let Some(conflict) = self.propagate_xor()? {
+                return Ok(Some(conflict));
+            }
+            if self.q_head == self.trail.len() {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// For each literal, the binary implications of the negation are examined, then the cardinality constraints and clauses watching the negation.
+    /// A single pass over the watches both moves watches to unassigned or true literals and notes any unit or conflicting clause.
+    fn propagate_clauses(&mut self) -> Result<Option<ClauseKey>, SolverError> {
+        while let Some(&literal) = self.trail.get(self.q_head) {
+            if self.stop_requested_in_propagation() {
+                return Ok(None);
+            }
+            self.q_head += 1;
+            self.propagations += 1;
+
+            let falsified = literal.negate();
+
+            // an implied literal is at the greatest level of the other literals of the clause, and so at the current level unless the falsified literal is below the current level
+            let current_level = self.levels.len() - 1;
+            let falsified_level =
+                self.decision_levels[falsified.v_id() as usize].unwrap_or(current_level);
+
+            for (clause_key, other) in &self.binary_watches[falsified.index()] {
+                match self.valuation.of_literal(*other) {
+                    Some(true) => {}
+                    Some(false) => return Ok(Some(*clause_key)),
+                    None => literal_update_at(
+                        *other,
+                        LiteralSource::StoredClause(*clause_key),
+                        falsified_level,
+                        &mut self.levels,
+                        &mut self.decision_levels,
+                        &mut self.valuation,
+                        &mut self.trail,
+                    )?,
                 }
+            }
+
+            if let Some(conflict) = self.propagate_cardinality(falsified)? {
+                return Ok(Some(conflict));
+            }
+
+            // the watches are taken from the solve, as new watches are only added for literals which are not false
+            let mut watches = std::mem::take(&mut self.watches[falsified.index()]);
+
+            // watches are kept by moving them to the front of the list, as those from index `kept` onwards are reviewed
+            let mut kept = 0;
+            let mut index = 0;
+            let length = watches.len();
+            let mut found_conflict = None;
+            let mut found_error = None;
+
+            while index < length {
+                let (clause_key, blocker) = unsafe { *watches.get_unchecked(index) };
+                index += 1;
 
-                // and, process whether any change to the watch literals is required
-                let working_clause_vec = match literal.polarity {
-                    true => &mut *variable.negative_watch_occurrences.get(),
-                    false => &mut *variable.positive_watch_occurrences.get(),
+                // a true blocker satisfies the clause, so there's no need to inspect it
+                if self.valuation.of_literal(blocker) == Some(true) {
+                    unsafe { *watches.get_unchecked_mut(kept) = (clause_key, blocker) };
+                    kept += 1;
+                    continue;
+                }
+
+                // the clause has been forgotten, and so the watch is dropped
+                if self.clause_store.is_deleted(clause_key) {
+                    continue;
+                }
+
+                time_statement!(stats::GET_STORED_TIME,
+                let watched_a = self.clause_store.get_watched(clause_key, Watch::A));
+
+                let (chosen_watch, other_watch) = if watched_a == falsified {
+                    (Watch::A, Watch::B)
+                } else if self.clause_store.get_watched(clause_key, Watch::B) == falsified {
+                    (Watch::B, Watch::A)
+                } else {
+                    // the clause no longer watches the literal
+                    continue;
                 };
 
-                let mut index = 0;
-                let mut length = working_clause_vec.len();
-
-                while index < length {
-                    if let Some(stored_clause) = retreive_mut(
-                        formula_clauses,
-                        learnt_clauses,
-                        *working_clause_vec.get_unchecked(index),
-                    ) {
-                        if stored_clause.get_watched(Watch::A).v_id == literal.v_id {
-                            if !watch_witnesses(valuation, stored_clause, Watch::B) {
-                                process_watches(valuation, variables, stored_clause, Watch::A);
+                let other = self.clause_store.get_watched(clause_key, other_watch);
+                let other_value = self.valuation.of_literal(other);
+
+                if other_value == Some(true) {
+                    unsafe { *watches.get_unchecked_mut(kept) = (clause_key, other) };
+                    kept += 1;
+                    continue;
+                }
+
+                time_statement!(stats::UPDATE_WATCH_TIME,
+                let update =
+                    self.clause_store.update_watch(clause_key, chosen_watch, &self.valuation));
+
+                match update {
+                    WatchUpdate::FromTo(_, to) => {
+                        self.watches[to.index()].push((clause_key, other));
+                    }
+                    WatchUpdate::NoUpdate => {
+                        unsafe { *watches.get_unchecked_mut(kept) = (clause_key, other) };
+                        kept += 1;
+
+                        match other_value {
+                            None => {
+                                let level = match falsified_level == current_level {
+                                    true => current_level,
+                                    false => self.implication_level(clause_key, other),
+                                };
+                                if let Err(e) = literal_update_at(
+                                    other,
+                                    LiteralSource::StoredClause(clause_key),
+                                    level,
+                                    &mut self.levels,
+                                    &mut self.decision_levels,
+                                    &mut self.valuation,
+                                    &mut self.trail,
+                                ) {
+                                    found_error = Some(e);
+                                    break;
+                                }
                             }
-                            index += 1;
-                        } else if stored_clause.get_watched(Watch::B).v_id == literal.v_id {
-                            if !watch_witnesses(valuation, stored_clause, Watch::A) {
-                                process_watches(valuation, variables, stored_clause, Watch::B);
+                            Some(_) => {
+                                found_conflict = Some(clause_key);
+                                break;
                             }
-                            index += 1;
-                        } else {
-                            working_clause_vec.swap_remove(index);
-                            length -= 1;
                         }
-                    } else {
-                        working_clause_vec.swap_remove(index);
-                        length -= 1;
                     }
                 }
             }
-        }
-        Err(ValuationStatus::Match) => match source {
-            LiteralSource::StoredClause(_) => {
-                // A literal may be implied by multiple clauses, so there's no need to do anything
+
+            // any watches not reviewed are kept
+            while index < length {
+                unsafe { *watches.get_unchecked_mut(kept) = *watches.get_unchecked(index) };
+                kept += 1;
+                index += 1;
             }
-            _ => panic!("Restatement of {} via {:?}", literal, source),
-        },
-        Err(ValuationStatus::Conflict) => panic!("Conflict given {literal} via {:?}", source),
-        Err(_) => todo!(),
+            watches.truncate(kept);
+            self.watches[falsified.index()] = watches;
+
+            if let Some(e) = found_error {
+                return Err(e);
+            }
+            if found_conflict.is_some() {
+                return Ok(found_conflict);
+            }
+        }
+
+        Ok(None)
     }
 }
 
-fn watch_witnesses(
-    valuation: &impl Valuation,
-    stored_clause: &mut StoredClause,
-    other_watch: Watch,
-) -> bool {
-    let (v_id, polarity) = stored_clause.get_watched_split(other_watch);
-    if let Some(p) = valuation.of_v_id(v_id) {
-        p == polarity
-    } else {
-        false
+impl Solve {
+    /// Examines the cardinality constraints watching the falsified literal, returning the key to some conflicting constraint, if found.
+    ///
+    /// If no replacement watch is found, every unwatched literal is false, and so either each watched literal which is not false is implied or the constraint conflicts.
+    fn propagate_cardinality(
+        &mut self,
+        falsified: Literal,
+    ) -> Result<Option<ClauseKey>, SolverError> {
+        let mut watches = std::mem::take(&mut self.cardinality_watches[falsified.index()]);
+        let current_level = self.levels.len() - 1;
+        let falsified_level =
+            self.decision_levels[falsified.v_id() as usize].unwrap_or(current_level);
+
+        let mut kept = 0;
+        let mut index = 0;
+        let mut outcome = Ok(None);
+
+        while index < watches.len() {
+            let key = watches[index];
+            index += 1;
+
+            match self
+                .clause_store
+                .update_cardinality_watch(key, falsified, &self.valuation)
+            {
+                WatchUpdate::FromTo(_, to) => self.cardinality_watches[to.index()].push(key),
+                WatchUpdate::NoUpdate => {
+                    watches[kept] = key;
+                    kept += 1;
+
+                    let degree = self.clause_store.degree(key);
+                    let constraint = self.clause_store.retreive(key);
+                    let watched = (0..=degree).map(|position| constraint.literal_at(position));
+                    let open = watched
+                        .clone()
+                        .filter(|literal| self.valuation.of_literal(*literal) != Some(false))
+                        .count();
+                    if open < degree {
+                        outcome = Ok(Some(key));
+                        break;
+                    }
+                    let implied = watched
+                        .filter(|literal| self.valuation.of_literal(*literal).is_none())
+                        .collect::<Vec<_>>();
+
+                    // as for a clause, the implied literals are at the greatest level of the false literals of the constraint
+                    let level = match (falsified_level == current_level, implied.first()) {
+                        (false, Some(&literal)) => self.implication_level(key, literal),
+                        _ => current_level,
+                    };
+
+                    for literal in implied {
+                        if let Err(e) = literal_update_at(
+                            literal,
+                            LiteralSource::StoredClause(key),
+                            level,
+                            &mut self.levels,
+                            &mut self.decision_levels,
+                            &mut self.valuation,
+                            &mut self.trail,
+                        ) {
+                            outcome = Err(e);
+                            break;
+                        }
+                    }
+                    if outcome.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // any watches not reviewed are kept
+        while index < watches.len() {
+            watches[kept] = watches[index];
+            kept += 1;
+            index += 1;
+        }
+        watches.truncate(kept);
+        self.cardinality_watches[falsified.index()] = watches;
+
+        outcome
     }
 }
 
-fn process_watches(
-    valuation: &impl Valuation,
-    variables: &[Variable],
-    stored_clause: &mut StoredClause,
-    chosen_watch: Watch,
-) -> WatchUpdate {
-    let update = stored_clause.update_watch(chosen_watch, valuation);
-    match update {
-        WatchUpdate::FromTo(_, to) => {
+/// Sets the literal on the valuation and records the literal at the relevant level and on the trail.
+/// Note: the consequences of the literal are not examined until the literal is propagated.
+///
+/// A literal which already has the value given is only noted if the literal is implied by some stored clause, as the literal may be implied by multiple clauses.
+/// Otherwise, restating a literal, or giving a literal whose negation is already valued, is an error.
+pub fn literal_update(
+    literal: Literal,
+    source: LiteralSource,
+    levels: &mut [Level],
+    decision_levels: &mut [Option<LevelIndex>],
+    valuation: &mut impl Valuation,
+    trail: &mut Vec<Literal>,
+) -> Result<(), SolverError> {
+    let level_index = match &source {
+        LiteralSource::Choice | LiteralSource::StoredClause(_) => levels.len() - 1,
+        LiteralSource::Assumption | LiteralSource::HobsonChoice | LiteralSource::Resolution(_) => 0,
+    };
+    literal_update_at(
+        literal,
+        source,
+        level_index,
+        levels,
+        decision_levels,
+        valuation,
+        trail,
+    )
+}
+
+/// As [literal_update], though with the literal recorded at the given level, as for a literal implied below the current level.
+pub fn literal_update_at(
+    literal: Literal,
+    source: LiteralSource,
+    level_index: LevelIndex,
+    levels: &mut [Level],
+    decision_levels: &mut [Option<LevelIndex>],
+    valuation: &mut impl Valuation,
+    trail: &mut Vec<Literal>,
+) -> Result<(), SolverError> {
+    match valuation.of_literal(literal) {
+        None => {
+            let _ = valuation.update_value(literal);
+            log::trace!("Set {source:?}: {literal}");
+            // if update occurrs, make records at the relevant level
             unsafe {
-                variables
-                    .get_unchecked(to.v_id())
-                    .watch_added(stored_clause.key(), to.polarity);
+                *decision_levels.get_unchecked_mut(literal.v_id() as usize) = Some(level_index)
             };
-            update
+            unsafe {
+                levels
+                    .get_unchecked_mut(level_index)
+                    .record_literal(literal, &source)?;
+            }
+            trail.push(literal);
+            Ok(())
         }
-        WatchUpdate::NoUpdate => update,
+        Some(true) => match source {
+            LiteralSource::StoredClause(_) => Ok(()),
+            _ => Err(SolverError::Restatement { literal, source }),
+        },
+        Some(false) => Err(SolverError::ConflictingLiteral { literal, source }),
     }
 }
 
 impl Solve {
-    pub fn literal_set_from_vec(&mut self, choices: Vec<VariableId>) {
-        choices.iter().for_each(|&v_id| {
+    pub fn literal_set_from_vec(&mut self, choices: Vec<VariableId>) -> Result<(), SolverError> {
+        for v_id in choices {
             let the_literal = Literal::new(v_id, false);
             literal_update(
                 the_literal,
                 LiteralSource::HobsonChoice,
                 &mut self.levels,
-                &self.variables,
+                &mut self.decision_levels,
                 &mut self.valuation,
-                &mut self.formula_clauses,
-                &mut self.learnt_clauses,
-            );
-            self.watch_q
-                .push_back((the_literal, LiteralSource::HobsonChoice));
-        });
+                &mut self.trail,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::solve::test_formulas::solve_of;
+
+    /// Chooses the literal at a fresh level.
+    fn choose(the_solve: &mut Solve, literal: Literal) {
+        the_solve.add_fresh_level();
+        literal_update(
+            literal,
+            LiteralSource::Choice,
+            &mut the_solve.levels,
+            &mut the_solve.decision_levels,
+            &mut the_solve.valuation,
+            &mut the_solve.trail,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn binary_implications() {
+        let mut the_solve = solve_of("1 2 0\n1 -2 0\n3 4 5 0\n");
+
+        choose(&mut the_solve, Literal::new(0, false));
+        let conflict = the_solve.propagate().unwrap().unwrap();
+
+        // 2 is implied by one binary clause, and the other binary clause is the conflict
+        let (source, implied) = the_solve.levels[1].observations()[0].clone();
+        assert_eq!(implied.v_id(), 1);
+        let LiteralSource::StoredClause(reason) = source else {
+            panic!("{implied} implied without a clause");
+        };
+        assert_ne!(reason, conflict);
+        let explanation = the_solve.explanation(reason, Some(implied));
+        assert_eq!(explanation.len(), 2);
+        assert!(explanation.contains(&implied));
+        let explanation = the_solve.explanation(conflict, None);
+        assert_eq!(explanation.len(), 2);
+        assert!(explanation
+            .iter()
+            .all(|literal| the_solve.valuation.of_literal(*literal) == Some(false)));
+
+        // the analysis of the conflict asserts 1 at level 0
+        assert!(matches!(
+            the_solve.attempt_fix(conflict).unwrap(),
+            SolveStatus::AssertingClause
+        ));
+        assert_eq!(the_solve.decision_levels[0], Some(0));
+        assert_eq!(the_solve.valuation.of_v_id(0), Some(true));
+    }
+
+    #[test]
+    fn true_blockers_are_passed_over() {
+        let mut the_solve = solve_of("1 2 3 4 0\n");
+        let key = the_solve.clause_store.keys().next().unwrap();
+        let watched = |the_solve: &Solve| {
+            [Watch::A, Watch::B].map(|watch| the_solve.clause_store.get_watched(key, watch))
+        };
+        let one = Literal::new(0, true);
+        let two = Literal::new(1, true);
+
+        // the watch of 2 moves, and 2 remains the blocker of the watch of 1
+        choose(&mut the_solve, two.negate());
+        assert!(the_solve.propagate().unwrap().is_none());
+        the_solve.backjump(0);
+        let moved = watched(&the_solve);
+        assert!(moved.contains(&one) && !moved.contains(&two));
+
+        // as 2 is true the clause is satisfied, and so the watch of 1 is kept though 1 is false
+        choose(&mut the_solve, two);
+        choose(&mut the_solve, one.negate());
+        assert!(the_solve.propagate().unwrap().is_none());
+        assert_eq!(watched(&the_solve), moved);
     }
 }
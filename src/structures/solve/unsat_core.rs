@@ -0,0 +1,117 @@
+use crate::structures::{
+    clause::Clause,
+    literal::{Literal, LiteralSource},
+    solve::{ClauseKey, Solve},
+};
+
+impl Solve {
+    /// If the solve is inconsistent and the core was tracked, the original indices of some clauses of the formula which are together unsatisfiable, in ascending order.
+    ///
+    /// The core is tracked if [SolveConfig::track_core](crate::structures::solve::config::SolveConfig::track_core) was set for each solve.
+    /// Clauses shared from another solve or refuting a cube have no origins, and XOR constraints have no original index, so in either case there is no core.
+    pub fn unsat_core(&self) -> Option<&[u32]> {
+        match self.origins_tracked && self.xors.constraints.is_empty() {
+            true => self.refutation.as_deref(),
+            false => None,
+        }
+    }
+
+    /// The next original index, as the clauses of the formula are indexed from 0 in order with any cardinality constraints following.
+    pub(super) fn fresh_original_index(&mut self) -> u32 {
+        self.original_count += 1;
+        self.original_count - 1
+    }
+
+    /// The original indices of the clauses from which the clause of the key follows, ignoring any literals false at level 0.
+    /// As origins are original indices rather than keys, these remain correct after the clauses used to learn the clause are deleted.
+    pub(super) fn origins(&self, key: ClauseKey) -> Vec<u32> {
+        match key {
+            ClauseKey::Formula(_) | ClauseKey::Cardinality(_) => {
+                self.clause_store.original_index(key).into_iter().collect()
+            }
+            ClauseKey::Learnt(_) => self.clause_store.retreive(key).origins().to_vec(),
+            ClauseKey::Xor(_) => vec![],
+        }
+    }
+
+    /// The original indices of the clauses from which the values of the given literals at level 0 follow.
+    pub(super) fn level_zero_origins(&self, literals: &[Literal]) -> Vec<u32> {
+        let mut seen = vec![false; self.variables.len()];
+        for literal in literals {
+            seen[literal.v_id() as usize] = true;
+        }
+
+        let mut origins = vec![];
+        for (source, literal) in self.levels[0].observations().iter().rev() {
+            if !seen[literal.v_id() as usize] {
+                continue;
+            }
+            match source {
+                LiteralSource::StoredClause(key) => {
+                    origins.extend(self.origins(*key));
+                    for v_id in self.explanation(*key, Some(*literal)).variables() {
+                        seen[v_id as usize] = true;
+                    }
+                }
+                LiteralSource::Resolution(resolved) => origins.extend(resolved),
+                _ => {}
+            }
+        }
+        origins
+    }
+
+    /// Notes the solve is inconsistent, with the core from the origins of the given conflict at level 0.
+    pub(super) fn refute(&mut self, conflict: ClauseKey) {
+        let literals = self.explanation(conflict, None);
+        let mut origins = self.level_zero_origins(&literals);
+        origins.extend(self.origins(conflict));
+        self.note_inconsistency(origins);
+    }
+
+    /// Notes the solve is inconsistent, with the core from the given origins.
+    pub(super) fn note_inconsistency(&mut self, mut origins: Vec<u32>) {
+        origins.sort_unstable();
+        origins.dedup();
+        self.inconsistent = true;
+        self.refutation = Some(origins);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{
+        formula::Formula,
+        solve::{config::SolveConfig, test_formulas::solve_of, SolveResult},
+    };
+
+    #[test]
+    fn core_of_original_indices() {
+        // clauses 2 and 5 are satisfiable on variables of their own, and the rest is unsatisfiable
+        let dimacs = "1 2 0\n-1 2 0\n5 6 0\n1 -2 0\n-1 -2 3 0\n-5 6 0\n-3 4 0\n-4 -3 0\n";
+        let formula = Formula::from_dimacs(dimacs).unwrap();
+        let clauses = formula.clauses.clone();
+        let mut the_solve = Solve::from_formula(formula).unwrap();
+        the_solve.set_config(SolveConfig {
+            track_core: true,
+            ..SolveConfig::default()
+        });
+        assert!(matches!(
+            the_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+
+        let core = the_solve.unsat_core().unwrap().to_vec();
+        assert!(!core.contains(&2) && !core.contains(&5));
+
+        let core_dimacs = core
+            .iter()
+            .map(|index| clauses[*index as usize].as_dimacs(&the_solve.variables) + "\n")
+            .collect::<String>();
+        let mut core_solve = solve_of(&core_dimacs);
+        assert!(matches!(
+            core_solve.do_solve().unwrap().0,
+            SolveResult::Unsatisfiable
+        ));
+    }
+}
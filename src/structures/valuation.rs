@@ -1,5 +1,7 @@
 use crate::structures::{literal::Literal, solve::Solve, variable::VariableId};
 
+/// A valuation indexed by literal, so the value of a literal is found at the index of the literal.
+/// The values of a literal and the negation of the literal are always kept in agreement.
 pub type ValuationVec = Vec<Option<bool>>;
 
 pub trait Valuation {
@@ -11,10 +13,14 @@ pub trait Valuation {
 
     fn of_v_id(&self, v_id: VariableId) -> Option<bool>;
 
+    fn of_literal(&self, literal: Literal) -> Option<bool>;
+
     fn check_literal(&self, literal: Literal) -> ValuationStatus;
 
     fn update_value(&mut self, literal: Literal) -> Result<(), ValuationStatus>;
 
+    fn clear_value(&mut self, v_id: VariableId);
+
     fn values(&self) -> impl Iterator<Item = Option<bool>>;
 }
 
@@ -26,11 +32,11 @@ pub enum ValuationStatus {
 
 impl Valuation for ValuationVec {
     fn new_for_variables(variable_count: usize) -> Self {
-        vec![None; variable_count]
+        vec![None; 2 * variable_count]
     }
 
     fn as_display_string(&self, solve: &Solve) -> String {
-        self.iter()
+        self.values()
             .enumerate()
             .filter(|(_, p)| p.is_some())
             .map(|(i, p)| {
@@ -46,7 +52,7 @@ impl Valuation for ValuationVec {
     }
 
     fn as_internal_string(&self) -> String {
-        self.iter()
+        self.values()
             .enumerate()
             .filter(|(_, p)| p.is_some())
             .map(|(i, p)| match p {
@@ -59,35 +65,47 @@ impl Valuation for ValuationVec {
     }
 
     fn of_v_id(&self, v_id: VariableId) -> Option<bool> {
-        unsafe { *self.get_unchecked(v_id as usize) }
+        self.of_literal(Literal::new(v_id, true))
+    }
+
+    /// Some(true) if the literal is true, Some(false) if the literal is false, and otherwise None
+    fn of_literal(&self, literal: Literal) -> Option<bool> {
+        unsafe { *self.get_unchecked(literal.index()) }
     }
 
     fn check_literal(&self, literal: Literal) -> ValuationStatus {
-        unsafe {
-            match self.get_unchecked(literal.v_id()) {
-                Some(already_set) if *already_set == literal.polarity => ValuationStatus::Match,
-                Some(_already_set) => ValuationStatus::Conflict,
-                None => ValuationStatus::NotSet,
-            }
+        match self.of_literal(literal) {
+            Some(true) => ValuationStatus::Match,
+            Some(false) => ValuationStatus::Conflict,
+            None => ValuationStatus::NotSet,
         }
     }
 
     fn update_value(&mut self, literal: Literal) -> Result<(), ValuationStatus> {
         log::trace!("Set literal: {}", literal);
-        unsafe {
-            match self.get_unchecked(literal.v_id()) {
-                Some(value) if *value != literal.polarity => Err(ValuationStatus::Conflict),
-                Some(_value) => Err(ValuationStatus::Match),
-                None => {
-                    *self.get_unchecked_mut(literal.v_id()) = Some(literal.polarity);
-
-                    Ok(())
+        match self.of_literal(literal) {
+            Some(false) => Err(ValuationStatus::Conflict),
+            Some(true) => Err(ValuationStatus::Match),
+            None => {
+                unsafe {
+                    *self.get_unchecked_mut(literal.index()) = Some(true);
+                    *self.get_unchecked_mut(literal.negate().index()) = Some(false);
                 }
+                Ok(())
             }
         }
     }
 
+    fn clear_value(&mut self, v_id: VariableId) {
+        let literal = Literal::new(v_id, true);
+        unsafe {
+            *self.get_unchecked_mut(literal.index()) = None;
+            *self.get_unchecked_mut(literal.negate().index()) = None;
+        }
+    }
+
+    /// The value of each variable, in order of variable id
     fn values(&self) -> impl Iterator<Item = Option<bool>> {
-        self.iter().cloned()
+        self.iter().skip(1).step_by(2).cloned()
     }
 }